@@ -721,8 +721,9 @@ fn can_annotation_help(
                 Err((shadowed_symbol, shadow, _new_symbol)) => {
                     env.problem(roc_problem::can::Problem::Shadowing {
                         original_region: shadowed_symbol.region,
-                        shadow,
-                        kind: ShadowKind::Variable,
+                        // point at the name, the region of the whole `as` can be malformed
+                        shadow: Loc::at(name.region, shadow.value),
+                        kind: scope.shadow_kind(shadowed_symbol.value),
                     });
 
                     return Type::Error;
@@ -846,7 +847,7 @@ fn can_annotation_help(
             let alias = scope.lookup_alias(symbol).unwrap();
             local_aliases.insert(symbol, alias.clone());
 
-            if vars.is_empty() && env.home == symbol.module_id() {
+            if vars.is_empty() && scope.is_home_symbol(symbol) {
                 let actual_var = var_store.fresh();
                 introduced_variables.insert_host_exposed_alias(symbol, actual_var);
                 Type::HostExposedAlias {
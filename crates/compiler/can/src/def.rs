@@ -2655,7 +2655,7 @@ fn to_pending_type_def<'a>(
                         env.problem(roc_problem::can::Problem::Shadowing {
                             original_region: shadowed_symbol.region,
                             shadow,
-                            kind: ShadowKind::Variable,
+                            kind: scope.shadow_kind(shadowed_symbol.value),
                         });
                         // Pretend the member isn't a part of the ability
                         continue;
@@ -73,7 +73,11 @@ impl<'a> Env<'a> {
 
         let module_name = ModuleName::from(module_name_str);
 
-        match self.module_ids.get_id(&module_name) {
+        let module_id = scope
+            .lookup_module_alias(&module_name)
+            .or_else(|| self.module_ids.get_id(&module_name));
+
+        match module_id {
             Some(module_id) => self.qualified_lookup_help(scope, module_id, ident, region),
             None => Err(RuntimeError::ModuleNotImported {
                 module_name,
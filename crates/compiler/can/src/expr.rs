@@ -9,7 +9,7 @@ use crate::num::{
 };
 use crate::pattern::{canonicalize_pattern, BindingsFromPattern, Pattern, PermitShadows};
 use crate::procedure::References;
-use crate::scope::Scope;
+use crate::scope::{DeprecationNote, Scope};
 use crate::traverse::{walk_expr, Visitor};
 use roc_collections::soa::Index;
 use roc_collections::{SendMap, VecMap, VecSet};
@@ -1455,7 +1455,7 @@ fn canonicalize_closure_body<'a>(
         // filter out top-level symbols those will be globally available, and don't need to be captured
         .filter(|s| !env.top_level_symbols.contains(s))
         // filter out imported symbols those will be globally available, and don't need to be captured
-        .filter(|s| s.module_id() == env.home)
+        .filter(|s| scope.is_home_symbol(*s))
         // filter out functions that don't close over anything
         .filter(|s| !new_output.non_closures.contains(s))
         .filter(|s| !output.non_closures.contains(s))
@@ -1791,10 +1791,18 @@ fn canonicalize_var_lookup(
     let can_expr = if module_name.is_empty() {
         // Since module_name was empty, this is an unqualified var.
         // Look it up in scope!
-        match scope.lookup_str(ident, region) {
-            Ok(symbol) => {
+        match scope.lookup_str_full(ident, region) {
+            Ok((symbol, deprecation)) => {
                 output.references.insert_value_lookup(symbol);
 
+                if let Some(DeprecationNote(note)) = deprecation {
+                    env.problem(Problem::DeprecatedLookup {
+                        symbol,
+                        note: note.clone(),
+                        region,
+                    });
+                }
+
                 if scope.abilities_store.is_ability_member_name(symbol) {
                     AbilityMember(
                         symbol,
@@ -1818,6 +1826,14 @@ fn canonicalize_var_lookup(
             Ok(symbol) => {
                 output.references.insert_value_lookup(symbol);
 
+                if let Some(DeprecationNote(note)) = scope.deprecation(symbol) {
+                    env.problem(Problem::DeprecatedLookup {
+                        symbol,
+                        note: note.clone(),
+                        region,
+                    });
+                }
+
                 if scope.abilities_store.is_ability_member_name(symbol) {
                     AbilityMember(
                         symbol,
@@ -282,11 +282,18 @@ pub fn canonicalize_module_defs<'a>(
     imported_abilities_state: PendingAbilitiesStore,
     exposed_imports: MutMap<Ident, (Symbol, Region)>,
     exposed_symbols: VecSet<Symbol>,
+    exposed_by_imports: Vec<(ModuleId, Vec<Ident>)>,
     symbols_from_requires: &[(Loc<Symbol>, Loc<TypeAnnotation<'a>>)],
     var_store: &mut VarStore,
 ) -> ModuleOutput {
     let mut can_exposed_imports = MutMap::default();
     let mut scope = Scope::new(home, exposed_ident_ids, imported_abilities_state);
+
+    for (module_id, idents) in exposed_by_imports {
+        if let Some(module_name) = module_ids.get_name(module_id) {
+            scope.register_module_exposed(module_id, module_name.as_str(), idents);
+        }
+    }
     let mut env = Env::new(arena, home, dep_idents, module_ids);
 
     for (name, alias) in aliases.into_iter() {
@@ -664,11 +671,11 @@ pub fn canonicalize_module_defs<'a>(
     for (ability, members) in scope
         .abilities_store
         .iter_abilities()
-        .filter(|(ab, _)| ab.module_id() == home)
+        .filter(|(ab, _)| scope.is_home_symbol(*ab))
     {
         exposed_but_not_defined.remove(&ability);
         members.iter().for_each(|member| {
-            debug_assert!(member.module_id() == home);
+            debug_assert!(scope.is_home_symbol(*member));
             exposed_but_not_defined.remove(member);
         });
     }
@@ -11,7 +11,7 @@ use roc_module::ident::{Ident, Lowercase, TagName};
 use roc_module::symbol::Symbol;
 use roc_parse::ast::{self, StrLiteral, StrSegment};
 use roc_parse::pattern::PatternType;
-use roc_problem::can::{MalformedPatternProblem, Problem, RuntimeError, ShadowKind};
+use roc_problem::can::{MalformedPatternProblem, Problem, RuntimeError};
 use roc_region::all::{Loc, Region};
 use roc_types::num::SingleQuoteBound;
 use roc_types::subs::{VarStore, Variable};
@@ -289,15 +289,15 @@ pub fn canonicalize_def_header_pattern<'a>(
                     };
                     Loc::at(region, can_pattern)
                 }
-                Err((original_region, shadow, new_symbol)) => {
+                Err((original, shadow, new_symbol)) => {
                     env.problem(Problem::RuntimeError(RuntimeError::Shadowing {
-                        original_region,
+                        original_region: original.region,
                         shadow: shadow.clone(),
-                        kind: ShadowKind::Variable,
+                        kind: scope.shadow_kind(original.value),
                     }));
                     output.references.insert_bound(new_symbol);
 
-                    let can_pattern = Pattern::Shadowed(original_region, shadow, new_symbol);
+                    let can_pattern = Pattern::Shadowed(original.region, shadow, new_symbol);
                     Loc::at(region, can_pattern)
                 }
             }
@@ -344,7 +344,7 @@ fn canonicalize_pattern_symbol<'a>(
                 env.problem(Problem::RuntimeError(RuntimeError::Shadowing {
                     original_region: shadowed_symbol.region,
                     shadow: shadow.clone(),
-                    kind: ShadowKind::Variable,
+                    kind: scope.shadow_kind(shadowed_symbol.value),
                 }));
                 output.references.insert_bound(new_symbol);
 
@@ -643,7 +643,7 @@ pub fn canonicalize_pattern<'a>(
                                 env.problem(Problem::RuntimeError(RuntimeError::Shadowing {
                                     original_region: shadowed_symbol.region,
                                     shadow: shadow.clone(),
-                                    kind: ShadowKind::Variable,
+                                    kind: scope.shadow_kind(shadowed_symbol.value),
                                 }));
 
                                 // No matter what the other patterns
@@ -715,7 +715,7 @@ pub fn canonicalize_pattern<'a>(
                                 env.problem(Problem::RuntimeError(RuntimeError::Shadowing {
                                     original_region: shadowed_symbol.region,
                                     shadow: shadow.clone(),
-                                    kind: ShadowKind::Variable,
+                                    kind: scope.shadow_kind(shadowed_symbol.value),
                                 }));
 
                                 // No matter what the other patterns
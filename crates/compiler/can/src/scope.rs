@@ -1,22 +1,31 @@
-use roc_collections::{VecMap, VecSet};
-use roc_module::ident::Ident;
+use roc_collections::{MutMap, MutSet, VecMap, VecSet};
+use roc_module::ident::{Ident, ModuleName};
 use roc_module::symbol::{IdentId, IdentIds, ModuleId, Symbol};
-use roc_problem::can::RuntimeError;
+use roc_problem::can::{RuntimeError, ShadowKind};
 use roc_region::all::{Loc, Region};
-use roc_types::subs::Variable;
+use roc_types::subs::{VarStore, VarStoreSnapshot, Variable};
 use roc_types::types::{Alias, AliasKind, AliasVar, Type};
 
 use crate::abilities::PendingAbilitiesStore;
 
+use roc_error_macros::internal_error;
+
 use bitvec::vec::BitVec;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU32, Ordering};
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
 
 // ability -> member names
 pub(crate) type PendingAbilitiesInScope = VecMap<Symbol, VecSet<Symbol>>;
 
 #[derive(Clone, Debug)]
 pub struct Scope {
-    /// The type aliases currently in scope
-    pub aliases: VecMap<Symbol, Alias>,
+    /// The type aliases currently in scope. Only modify through [Scope::aliases_mut]
+    aliases: VecMap<Symbol, Alias>,
+
+    /// Bumped whenever `aliases` may have changed, see [Scope::aliases_generation]
+    aliases_generation: u32,
 
     /// The abilities currently in scope, and their implementors.
     pub abilities_store: PendingAbilitiesStore,
@@ -31,6 +40,19 @@ pub struct Scope {
     /// Identifiers that are imported (and introduced in the header)
     imports: Vec<(Ident, Symbol, Region)>,
 
+    /// The number of imports that every module starts out with, see [Scope::new]
+    default_imports_count: usize,
+
+    /// Imported symbols that a lookup resolved to, see [Scope::unused_imports]
+    used_imports: RefCell<MutSet<Symbol>>,
+
+    /// Whether a lookup resolved to the local symbol, by [IdentId], see [Scope::unused_idents]
+    used_locals: RefCell<BitVec>,
+
+    /// Imported symbols whose name a local binding tried to shadow. Later lookups of the name
+    /// were meant for the local, so they are not uses of the import.
+    shadowed_imports: VecSet<Symbol>,
+
     /// Shadows of an ability member, for example a local specialization of `eq` for the ability
     /// member `Eq has eq : a, a -> Bool | a has Eq` gets a shadow symbol it can use for its
     /// implementation.
@@ -38,10 +60,210 @@ pub struct Scope {
     /// Only one shadow of an ability member is permitted per scope.
     shadows: VecMap<Symbol, Loc<Symbol>>,
 
+    /// Identifiers that are known to be defined later on, but have not been introduced yet.
+    /// Used to give a better error message when such an identifier is used too early.
+    pending: Vec<(Ident, Region)>,
+
+    /// The names exposed by imported modules. When an unqualified lookup fails, this is used
+    /// to suggest qualifying the name with the module that exposes it.
+    exposed_elsewhere: VecMap<ModuleId, (ModuleName, Vec<Ident>)>,
+
+    /// Aliases defined in this module whose name is also the name of a builtin type
+    shadowed_builtin_aliases: Vec<ShadowedBuiltinAlias>,
+
+    /// Placeholder symbols for names that failed to resolve, see [Scope::lookup_or_recover]
+    recovered: VecMap<Ident, Symbol>,
+
+    /// Symbols that should no longer be used, see [Scope::mark_deprecated]
+    deprecated: VecMap<Symbol, DeprecationNote>,
+
+    /// Other names that modules can be referred to by in qualified lookups, see
+    /// [Scope::add_module_alias]
+    module_aliases: VecMap<ModuleName, ModuleId>,
+
+    /// Top-level names that are in scope ahead of their definition, see
+    /// [Scope::pre_introduce_top_level]
+    pre_introduced: VecSet<Symbol>,
+
+    /// The number of [Scope::inner_scope]s that are currently entered, 0 at the top level
+    inner_scope_depth: u32,
+
     /// Identifiers that are in scope, and defined in the current module
     pub locals: ScopedIdentIds,
+
+    /// Identifies this scope (and its clones) in the snapshots taken from it
+    id: u32,
+
+    /// Bumped whenever something is removed from the scope without recording how to undo it,
+    /// which makes the snapshots taken before unusable
+    generation: u32,
+
+    /// How to undo the changes made while a snapshot is open, see [Scope::snapshot]
+    undo_log: RefCell<UndoLog>,
+}
+
+fn next_scope_id() -> u32 {
+    static NEXT_SCOPE_ID: AtomicU32 = AtomicU32::new(0);
+
+    NEXT_SCOPE_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The maximum number of results of [Scope::idents_matching]
+pub const MAX_COMPLETIONS: usize = 100;
+
+/// A name in scope, as suggested by [Scope::idents_matching]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Completion {
+    pub ident: Ident,
+    pub symbol: Symbol,
+    pub kind: CompletionKind,
+    pub region: Region,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompletionKind {
+    Value,
+    Alias,
+    Opaque,
+    AbilityMember,
+    /// Imported from a module that is not a builtin
+    Import,
+    Builtin,
+}
+
+/// The state of a [Scope] at some point, to later find what was added since with
+/// [Scope::changes_since], or to go back to with [Scope::rollback_to].
+///
+/// Identifiers and imports are only ever added, so their counts are enough to go back. Other
+/// changes are recorded in the scope's undo log for as long as the snapshot is open.
+#[derive(Debug)]
+pub struct ScopeSnapshot {
+    ident_count: usize,
+    imports_count: usize,
+    undo_log_len: usize,
+    /// Set by [Scope::snapshot_with_var_store]
+    var_store: Option<VarStoreSnapshot>,
+    scope_id: u32,
+    generation: u32,
+}
+
+/// The changes to a [Scope] that are recorded while at least one snapshot is open
+#[derive(Clone, Debug, Default)]
+struct UndoLog {
+    entries: Vec<Undo>,
+    /// Snapshots that were neither rolled back to nor committed yet
+    open_snapshots: u32,
+}
+
+/// A change to a [Scope] that cannot be undone by truncating to a length. Each entry holds
+/// what was there before the change.
+#[derive(Clone, Debug)]
+enum Undo {
+    /// The alias of the symbol, if it had one
+    Alias(Symbol, Option<Alias>),
+    /// The shadow of the ability member, if it had one
+    Shadow(Symbol, Option<Loc<Symbol>>),
+    /// Whether the local with this index was in scope, and its region
+    Local(usize, bool, Region),
+    /// The local with this index had a generated name, see [ScopedIdentIds::gen_unique]
+    GeneratedName(usize),
+    PendingPushed,
+    PendingRemoved(usize, (Ident, Region)),
+    ExposedElsewhere(ModuleId, Option<(ModuleName, Vec<Ident>)>),
+    ShadowedBuiltinAliasPushed,
+    Recovered(Ident, Option<Symbol>),
+    Deprecated(Symbol, Option<DeprecationNote>),
+    ModuleAlias(ModuleName, Option<ModuleId>),
+    /// Whether the symbol was pre-introduced
+    PreIntroduced(Symbol, bool),
+    /// The import was not used yet
+    UsedImport(Symbol),
+    /// The local with this index was not used yet
+    UsedLocal(usize),
+    ShadowedImport(Symbol),
+}
+
+/// Everything that was added to a [Scope] since a [ScopeSnapshot] was taken.
+#[derive(Clone, Debug)]
+pub struct ScopeDelta {
+    first_ident_index: usize,
+    /// All identifiers created since the snapshot, also those that are not in scope. The names
+    /// generated by [Scope::gen_unique_symbol] are `None`, so that they are generated again.
+    new_idents: Vec<Option<Ident>>,
+    /// Local bindings that came into scope, in the order of their symbols
+    pub introduced: Vec<(Ident, Symbol, Region)>,
+    pub imports: Vec<(Ident, Symbol, Region)>,
+    /// Aliases that were added or overwritten
+    pub aliases: Vec<(Symbol, Alias)>,
+    /// Shadows of ability members, i.e. their specializations
+    pub specializations: Vec<(Symbol, Loc<Symbol>)>,
+    /// Names that resolved to the first symbol at the snapshot, and to the second one now
+    pub overwritten: Vec<(Symbol, Symbol)>,
+}
+
+/// A name of a group of definitions that cannot be introduced, see
+/// [Scope::introduce_recursive_group]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GroupConflict {
+    /// The name is defined more than once within the group
+    Duplicate {
+        original: Loc<Ident>,
+        duplicate: Loc<Ident>,
+    },
+    /// The name is already in scope
+    Shadows {
+        original: Loc<Symbol>,
+        shadow: Loc<Ident>,
+    },
+}
+
+/// An identifier could not be introduced, because its name is already in scope
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShadowError {
+    pub original: Loc<Symbol>,
+    pub shadow: Loc<Ident>,
 }
 
+/// [Scope::copy_alias] was asked to copy an alias that is not in scope
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AliasNotFound(pub Symbol);
+
+/// Whether a binding has a name that is exposed by the module header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WasExposed(pub bool);
+
+/// How [Scope::bind] brings a name into scope
+#[derive(Clone, Copy, Debug)]
+enum Binding {
+    /// A local, with an IdentId that was already allocated for it
+    Local(IdentId),
+    /// A symbol from another module
+    Import(Symbol),
+}
+
+/// Where a symbol that is in scope was defined
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolOrigin {
+    Local,
+    Imported(ModuleId),
+}
+
+/// A type alias defined in the current module that has the same name as a builtin type, e.g. a
+/// module that defines its own `Result a b : [Ok a, Err b]`.
+///
+/// This is not an error, but annotations in the module will refer to the local alias, which
+/// is usually not what the author expects.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShadowedBuiltinAlias {
+    pub name: Ident,
+    pub builtin: Symbol,
+    pub region: Region,
+}
+
+/// Why a symbol is deprecated, and what to use instead
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeprecationNote(pub Box<str>);
+
 impl Scope {
     pub fn new(
         home: ModuleId,
@@ -54,23 +276,264 @@ impl Scope {
             // Add all tag names we might want to suggest as hints in error messages.
             .chain(Symbol::symbols_in_scope_for_hints());
 
-        let default_imports = default_imports.map(|(a, (b, c))| (a, b, c)).collect();
+        let default_imports: Vec<_> = default_imports.map(|(a, (b, c))| (a, b, c)).collect();
 
         Scope {
             home,
             exposed_ident_count: initial_ident_ids.len(),
             locals: ScopedIdentIds::from_ident_ids(home, initial_ident_ids),
             aliases: VecMap::default(),
+            aliases_generation: 0,
             abilities_store: starting_abilities_store,
             shadows: VecMap::default(),
+            pending: Vec::new(),
+            exposed_elsewhere: VecMap::default(),
+            shadowed_builtin_aliases: Vec::new(),
+            recovered: VecMap::default(),
+            deprecated: VecMap::default(),
+            module_aliases: VecMap::default(),
+            pre_introduced: VecSet::default(),
+            inner_scope_depth: 0,
+            default_imports_count: default_imports.len(),
+            used_imports: RefCell::default(),
+            used_locals: RefCell::default(),
+            shadowed_imports: VecSet::default(),
             imports: default_imports,
+            id: next_scope_id(),
+            generation: 0,
+            undo_log: RefCell::default(),
+        }
+    }
+
+    /// A scope without the default imports, aliases, or abilities.
+    ///
+    /// Only meant for tests and tooling that want full control over what is in scope;
+    /// canonicalization of real modules should always go through [`Scope::new`].
+    pub fn new_empty(home: ModuleId) -> Scope {
+        Scope {
+            home,
+            exposed_ident_count: 0,
+            locals: ScopedIdentIds::from_ident_ids(home, IdentIds::default()),
+            aliases: VecMap::default(),
+            aliases_generation: 0,
+            abilities_store: PendingAbilitiesStore::default(),
+            shadows: VecMap::default(),
+            pending: Vec::new(),
+            exposed_elsewhere: VecMap::default(),
+            shadowed_builtin_aliases: Vec::new(),
+            recovered: VecMap::default(),
+            deprecated: VecMap::default(),
+            module_aliases: VecMap::default(),
+            pre_introduced: VecSet::default(),
+            inner_scope_depth: 0,
+            imports: Vec::new(),
+            default_imports_count: 0,
+            used_imports: RefCell::default(),
+            used_locals: RefCell::default(),
+            shadowed_imports: VecSet::default(),
+            id: next_scope_id(),
+            generation: 0,
+            undo_log: RefCell::default(),
+        }
+    }
+
+    pub fn home(&self) -> ModuleId {
+        self.home
+    }
+
+    /// Whether `symbol` is defined in this module, rather than imported from another one
+    pub fn is_home_symbol(&self, symbol: Symbol) -> bool {
+        symbol.module_id() == self.home
+    }
+
+    /// The symbols in scope that are defined in this module
+    pub fn home_symbols(&self) -> impl Iterator<Item = Symbol> + '_ {
+        self.iter_bindings()
+            .map(|(_, symbol, _)| symbol)
+            .filter(|symbol| self.is_home_symbol(*symbol))
+    }
+
+    /// The symbols of the bindings this module introduced that are in scope, with the region
+    /// they were introduced at, in a stable order for code generation.
+    ///
+    /// Imports (which includes the builtins in scope by default) and symbols that are not in
+    /// scope, like the shadows of ability members made for their specializations, are skipped.
+    /// Symbols come in the order of their IdentIds, which is the order they were introduced in,
+    /// except that names exposed by the module header come first, in the order of the header.
+    pub fn user_symbols_in_order(&self) -> impl Iterator<Item = (Symbol, Region)> + '_ {
+        self.locals
+            .bindings_in_scope()
+            .map(|(_, symbol, region)| (symbol, region))
+    }
+
+    /// The other modules that the bindings and aliases in scope come from, each once and in
+    /// the order they are first encountered.
+    pub fn referenced_modules(&self) -> impl Iterator<Item = ModuleId> {
+        let symbols = self
+            .iter_bindings()
+            .map(|(_, symbol, _)| symbol)
+            .chain(self.aliases.keys().copied());
+
+        let mut modules = VecSet::default();
+
+        for symbol in symbols {
+            if !self.is_home_symbol(symbol) {
+                modules.insert(symbol.module_id());
+            }
         }
+
+        modules.into_iter()
     }
 
     pub fn lookup(&self, ident: &Ident, region: Region) -> Result<Symbol, RuntimeError> {
         self.lookup_str(ident.as_str(), region)
     }
 
+    /// Like [Self::lookup], but also returns the deprecation note of the symbol, if any.
+    pub fn lookup_full(
+        &self,
+        ident: &Ident,
+        region: Region,
+    ) -> Result<(Symbol, Option<&DeprecationNote>), RuntimeError> {
+        self.lookup_str_full(ident.as_str(), region)
+    }
+
+    pub fn lookup_str_full(
+        &self,
+        ident: &str,
+        region: Region,
+    ) -> Result<(Symbol, Option<&DeprecationNote>), RuntimeError> {
+        let symbol = self.lookup_str(ident, region)?;
+
+        Ok((symbol, self.deprecation(symbol)))
+    }
+
+    /// Mark `symbol` as deprecated, so that every use of it is reported with `note`. Both local
+    /// and imported symbols can be marked.
+    pub fn mark_deprecated(&mut self, symbol: Symbol, note: Box<str>) {
+        let old = self.deprecated.insert(symbol, DeprecationNote(note));
+        self.log(Undo::Deprecated(symbol, old));
+    }
+
+    pub fn deprecation(&self, symbol: Symbol) -> Option<&DeprecationNote> {
+        self.deprecated.get(&symbol)
+    }
+
+    /// Let qualified lookups refer to `module_id` as `alias`, e.g. `Json` for `Encode.Json`.
+    ///
+    /// Returns the module that `alias` referred to before, if any, so that a conflicting
+    /// re-import can be reported.
+    pub fn add_module_alias(&mut self, alias: ModuleName, module_id: ModuleId) -> Option<ModuleId> {
+        let old = self.module_aliases.insert(alias.clone(), module_id);
+        self.log(Undo::ModuleAlias(alias, old));
+
+        old
+    }
+
+    pub fn remove_module_alias(&mut self, alias: &ModuleName) -> Option<ModuleId> {
+        let old = self
+            .module_aliases
+            .remove(alias)
+            .map(|(_, module_id)| module_id);
+
+        if old.is_some() {
+            self.log(Undo::ModuleAlias(alias.clone(), old));
+        }
+
+        old
+    }
+
+    pub fn lookup_module_alias(&self, alias: &ModuleName) -> Option<ModuleId> {
+        self.module_aliases.get(alias).copied()
+    }
+
+    /// Like [Self::lookup], but also tells whether the symbol is defined in this module.
+    pub fn lookup_with_origin(
+        &self,
+        ident: &Ident,
+        region: Region,
+    ) -> Result<(Symbol, SymbolOrigin), RuntimeError> {
+        let symbol = self.lookup(ident, region)?;
+
+        let origin = if self.is_home_symbol(symbol) {
+            SymbolOrigin::Local
+        } else {
+            SymbolOrigin::Imported(symbol.module_id())
+        };
+
+        Ok((symbol, origin))
+    }
+
+    /// Like [Self::lookup], but a successful lookup also pushes the symbol and the region of
+    /// this use of it to `refs`, e.g. to build an index of all references to a symbol while
+    /// canonicalizing. Failed lookups are not recorded.
+    pub fn lookup_recording(
+        &self,
+        ident: &Ident,
+        region: Region,
+        refs: &mut Vec<(Symbol, Region)>,
+    ) -> Result<Symbol, RuntimeError> {
+        let symbol = self.lookup(ident, region)?;
+
+        refs.push((symbol, region));
+
+        Ok(symbol)
+    }
+
+    /// Like [Self::lookup], but keeps going when the lookup fails.
+    ///
+    /// A missing name gets a placeholder symbol, so that every reference to it resolves to the
+    /// same symbol. The error is only returned for the first reference. Placeholders are never
+    /// in scope, so they do not show up as suggestions.
+    pub fn lookup_or_recover(
+        &mut self,
+        ident: &Ident,
+        region: Region,
+    ) -> (Symbol, Option<RuntimeError>) {
+        match self.lookup(ident, region) {
+            Ok(symbol) => (symbol, None),
+            Err(error) => match self.recovered.get(ident) {
+                Some(placeholder) => (*placeholder, None),
+                None => {
+                    let placeholder = self.scopeless_symbol(ident, region);
+                    let old = self.recovered.insert(ident.clone(), placeholder);
+                    self.log(Undo::Recovered(ident.clone(), old));
+
+                    (placeholder, Some(error))
+                }
+            },
+        }
+    }
+
+    /// Every symbol that was bound to `ident` in this module, in source order. That includes
+    /// the current binding, shadows, and bindings of inner scopes that were since left.
+    ///
+    /// Placeholders for names that were not in scope are not definitions, and are not included.
+    pub fn all_symbols_for_name(&self, ident: &Ident) -> Vec<(Symbol, Region)> {
+        let name = normalize(ident.as_str());
+
+        let mut definitions: Vec<_> = self
+            .locals
+            .ident_ids
+            .get_id_many(&name)
+            .filter(|ident_id| {
+                let index = ident_id.index();
+
+                // exposed idents are known before they are defined
+                index >= self.exposed_ident_count || self.locals.in_scope[index]
+            })
+            .map(|ident_id| {
+                let symbol = Symbol::new(self.home, ident_id);
+                (symbol, self.locals.regions[ident_id.index()])
+            })
+            .filter(|(symbol, _)| !self.recovered.values().any(|p| p == symbol))
+            .collect();
+
+        definitions.sort_by_key(|(_, region)| region.start());
+
+        definitions
+    }
+
     pub fn lookup_ability_member_shadow(&self, member: Symbol) -> Option<Symbol> {
         self.shadows.get(&member).map(|loc_shadow| loc_shadow.value)
     }
@@ -86,8 +549,41 @@ impl Scope {
         use ContainsIdent::*;
 
         match self.scope_contains_ident(ident) {
-            InScope(symbol, _) => Ok(symbol),
+            InScope(symbol, _) => {
+                self.mark_used(symbol);
+
+                Ok(symbol)
+            }
             NotInScope(_) | NotPresent => {
+                if let Some(definition_region) = self.pending_region(ident) {
+                    return Err(RuntimeError::UsedBeforeDefinition {
+                        usage: Loc::at(region, Ident::from(ident)),
+                        definition_region,
+                    });
+                }
+
+                let exposed_by: Vec<_> = self
+                    .exposed_elsewhere
+                    .values()
+                    .filter(|(_, idents)| idents.iter().any(|exposed| exposed.as_str() == ident))
+                    .map(|(module_name, _)| module_name.clone())
+                    .take(2)
+                    .collect();
+
+                if !exposed_by.is_empty() {
+                    return Err(RuntimeError::LookupNotInScopeButExposed {
+                        usage: Loc::at(region, Ident::from(ident)),
+                        exposed_by,
+                    });
+                }
+
+                if let Some(similar) = self.looks_alike(ident) {
+                    return Err(RuntimeError::LookupNotInScopeButLooksAlike {
+                        usage: Loc::at(region, Ident::from(ident)),
+                        similar,
+                    });
+                }
+
                 let error = RuntimeError::LookupNotInScope(
                     Loc {
                         region,
@@ -101,6 +597,93 @@ impl Scope {
         }
     }
 
+    /// Check that every name in the module header's exposed list is defined in this module.
+    /// Run this once all top-level names have been introduced.
+    ///
+    /// Each missing name gets the same error as a lookup of it would, at its region in the
+    /// header. Imported names are not defined here, so exposing them is an error as well.
+    pub fn check_exposed(&self, exposed: &[Loc<Ident>]) -> Vec<RuntimeError> {
+        exposed
+            .iter()
+            .filter_map(
+                |Loc { region, value }| match self.lookup_str(value.as_str(), *region) {
+                    Ok(symbol) if self.is_home_symbol(symbol) => None,
+                    Ok(_) => Some(RuntimeError::LookupNotInScope(
+                        Loc::at(*region, value.clone()),
+                        self.idents_in_scope().map(|v| v.as_ref().into()).collect(),
+                    )),
+                    Err(error) => Some(error),
+                },
+            )
+            .collect()
+    }
+
+    /// Find the names that the module header's exposed list contains more than once.
+    ///
+    /// Every repeated occurrence is returned with the region of the first one and its own
+    /// region. Unlike [`Scope::check_exposed`] this only looks at the list, not at the scope.
+    pub fn validate_exposes(&self, exposed: &[Loc<Ident>]) -> Vec<(Ident, Region, Region)> {
+        let mut first_regions: VecMap<&Ident, Region> = VecMap::default();
+        let mut duplicates = Vec::new();
+
+        for Loc { region, value } in exposed {
+            match first_regions.get(&value) {
+                Some(first_region) => duplicates.push((value.clone(), *first_region, *region)),
+                None => {
+                    first_regions.insert(value, *region);
+                }
+            }
+        }
+
+        duplicates
+    }
+
+    /// Register an identifier that will be introduced later on, at `region`.
+    ///
+    /// Looking up such an identifier before it is introduced results in a
+    /// [`RuntimeError::UsedBeforeDefinition`] rather than a [`RuntimeError::LookupNotInScope`].
+    pub fn register_pending(&mut self, ident: Ident, region: Region) {
+        self.pending.push((ident, region));
+        self.log(Undo::PendingPushed);
+    }
+
+    /// Register the identifiers exposed by an imported module, so that a failed unqualified
+    /// lookup of one of them can suggest the qualified name instead.
+    pub fn register_module_exposed(
+        &mut self,
+        module: ModuleId,
+        module_name: &str,
+        idents: impl IntoIterator<Item = Ident>,
+    ) {
+        let idents = idents.into_iter().collect();
+
+        let old = self
+            .exposed_elsewhere
+            .insert(module, (ModuleName::from(module_name), idents));
+        self.log(Undo::ExposedElsewhere(module, old));
+    }
+
+    /// An identifier in scope that is written differently, but looks the same as `ident`
+    fn looks_alike(&self, ident: &str) -> Option<Ident> {
+        // compatibility normalization also equates e.g. ligatures and full-width letters
+        let key: String = ident.nfkc().collect();
+
+        self.idents_in_scope()
+            .find(|candidate| candidate.as_str().nfkc().eq(key.chars()))
+    }
+
+    fn pending_region(&self, ident: &str) -> Option<Region> {
+        self.pending
+            .iter()
+            .find(|(pending, _)| pending.as_str() == ident)
+            .map(|(_, region)| *region)
+    }
+
+    /// The number of identifiers currently in scope, both imported and local.
+    pub fn num_idents(&self) -> usize {
+        self.idents_in_scope().count()
+    }
+
     fn idents_in_scope(&self) -> impl Iterator<Item = Ident> + '_ {
         let it1 = self.locals.idents_in_scope();
         let it2 = self.imports.iter().map(|t| t.0.clone());
@@ -108,6 +691,173 @@ impl Scope {
         it2.chain(it1)
     }
 
+    /// All bindings currently in scope, both imported and local, together with their symbol
+    /// and the region where they were introduced.
+    ///
+    /// Identifiers that are not (or no longer) in scope, e.g. those of shadowing ability member
+    /// specializations or of an inner scope that has been exited, are skipped.
+    pub fn iter_bindings(&self) -> impl Iterator<Item = (Ident, Symbol, Region)> + '_ {
+        let it1 = self.locals.bindings_in_scope();
+        let it2 = self
+            .imports
+            .iter()
+            .map(|(ident, symbol, region)| (ident.clone(), *symbol, *region));
+
+        it2.chain(it1)
+    }
+
+    /// A human-readable listing of the bindings, aliases, abilities and ability member
+    /// specializations in scope, e.g. for snapshot tests of canonicalization.
+    ///
+    /// Unlike the `Debug` output, entries are sorted (by name, then symbol), so the dump does not
+    /// depend on the order things were added in. Symbols of this module are written as their
+    /// name and ident index, e.g. `foo#3`.
+    pub fn dump(&self) -> String {
+        use std::fmt::Write;
+
+        let symbol_name = |symbol: Symbol| -> String {
+            match self.locals.ident_ids.get_name(symbol.ident_id()) {
+                Some(name) if self.is_home_symbol(symbol) => {
+                    format!("{}#{}", name, symbol.ident_id().index())
+                }
+                _ => format!("{:?}", symbol),
+            }
+        };
+
+        let mut out = String::new();
+
+        let mut bindings: Vec<_> = self.iter_bindings().collect();
+        bindings.sort_by(|(a, a_symbol, _), (b, b_symbol, _)| {
+            (a.as_str(), a_symbol).cmp(&(b.as_str(), b_symbol))
+        });
+
+        out.push_str("bindings:\n");
+        for (ident, symbol, region) in bindings {
+            let _ = writeln!(out, "    {} {} {:?}", ident, symbol_name(symbol), region);
+        }
+
+        let mut aliases: Vec<_> = self
+            .aliases
+            .iter()
+            .map(|(symbol, alias)| (symbol_name(*symbol), alias.kind))
+            .collect();
+        aliases.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        out.push_str("aliases:\n");
+        for (name, kind) in aliases {
+            let _ = writeln!(out, "    {} {:?}", name, kind);
+        }
+
+        let mut abilities: Vec<_> = self
+            .abilities_store
+            .iter_abilities()
+            .map(|(ability, members)| {
+                let members: Vec<_> = members.iter().map(|m| symbol_name(*m)).collect();
+                (symbol_name(ability), members.join(", "))
+            })
+            .collect();
+        abilities.sort();
+
+        out.push_str("abilities:\n");
+        for (ability, members) in abilities {
+            let _ = writeln!(out, "    {}: {}", ability, members);
+        }
+
+        let mut specializations: Vec<_> = self
+            .shadows
+            .iter()
+            .map(|(member, shadow)| {
+                (
+                    symbol_name(*member),
+                    symbol_name(shadow.value),
+                    shadow.region,
+                )
+            })
+            .collect();
+        specializations
+            .sort_by(|(a, a_shadow, _), (b, b_shadow, _)| (a, a_shadow).cmp(&(b, b_shadow)));
+
+        out.push_str("specializations:\n");
+        for (member, shadow, region) in specializations {
+            let _ = writeln!(out, "    {} -> {} {:?}", member, shadow, region);
+        }
+
+        out
+    }
+
+    /// The names in scope that start with `prefix`, e.g. for tab completion.
+    ///
+    /// Local definitions come first, then imports, each sorted by name. When no name starts with
+    /// `prefix` exactly, names that do so ignoring case are returned instead. At most
+    /// [MAX_COMPLETIONS] results are returned.
+    pub fn idents_matching(&self, prefix: &str) -> Vec<Completion> {
+        let mut completions = self.completions_where(|ident| ident.starts_with(prefix));
+
+        if completions.is_empty() {
+            let prefix = prefix.to_lowercase();
+
+            completions =
+                self.completions_where(|ident| ident.to_lowercase().starts_with(prefix.as_str()));
+        }
+
+        completions.truncate(MAX_COMPLETIONS);
+
+        completions
+    }
+
+    fn completions_where(&self, matches: impl Fn(&str) -> bool) -> Vec<Completion> {
+        let mut completions: Vec<_> = self
+            .iter_bindings()
+            .filter(|(ident, symbol, _)| {
+                // a name can be bound more than once, e.g. an import and a local of the same
+                // name; only offer the binding that a lookup of the name resolves to
+                matches(ident.as_str())
+                    && matches!(
+                        self.scope_contains_ident(ident.as_str()),
+                        ContainsIdent::InScope(resolved, _) if resolved == *symbol
+                    )
+            })
+            .map(|(ident, symbol, region)| Completion {
+                kind: self.completion_kind(symbol),
+                ident,
+                symbol,
+                region,
+            })
+            .collect();
+
+        completions.sort_by(|a, b| {
+            let rank = |completion: &Completion| match completion.kind {
+                CompletionKind::Import => 1,
+                CompletionKind::Builtin => 2,
+                _ => 0,
+            };
+
+            (rank(a), a.ident.as_str()).cmp(&(rank(b), b.ident.as_str()))
+        });
+
+        completions.dedup_by(|a, b| a.ident == b.ident);
+
+        completions
+    }
+
+    fn completion_kind(&self, symbol: Symbol) -> CompletionKind {
+        if !self.is_home_symbol(symbol) {
+            if symbol.is_builtin() {
+                CompletionKind::Builtin
+            } else {
+                CompletionKind::Import
+            }
+        } else if self.abilities_store.is_ability_member_name(symbol) {
+            CompletionKind::AbilityMember
+        } else {
+            match self.aliases.get(&symbol).map(|alias| alias.kind) {
+                Some(AliasKind::Structural) => CompletionKind::Alias,
+                Some(AliasKind::Opaque) => CompletionKind::Opaque,
+                None => CompletionKind::Value,
+            }
+        }
+    }
+
     /// Check if there is an opaque type alias referenced by `opaque_ref` referenced in the
     /// current scope. E.g. `@Age` must reference an opaque `Age` declared in this module, not any
     /// other!
@@ -192,7 +942,69 @@ impl Scope {
         }
     }
 
+    fn mark_used(&self, symbol: Symbol) {
+        if self.is_home_symbol(symbol) {
+            let index = symbol.ident_id().index();
+            let mut used_locals = self.used_locals.borrow_mut();
+
+            if index >= used_locals.len() {
+                used_locals.resize(index + 1, false);
+            }
+
+            if !used_locals.replace(index, true) {
+                self.log(Undo::UsedLocal(index));
+            }
+        } else if !self.shadowed_imports.contains(&symbol)
+            && self.used_imports.borrow_mut().insert(symbol)
+        {
+            self.log(Undo::UsedImport(symbol));
+        }
+    }
+
+    /// A local binding tried to shadow `original`. If that is an import, the lookups of its
+    /// name that follow are meant for the local.
+    fn note_shadowed(&mut self, original: Symbol) {
+        if !self.is_home_symbol(original) && !self.shadowed_imports.insert(original) {
+            self.log(Undo::ShadowedImport(original));
+        }
+    }
+
+    /// The imports that no lookup resolved to, e.g. to warn about imports that can be removed.
+    ///
+    /// The imports that every module starts out with are not included. An import that a local
+    /// binding shadows is unused, unless it was looked up before the local was introduced.
+    pub fn unused_imports(&self) -> Vec<(Ident, Symbol, Region)> {
+        let used = self.used_imports.borrow();
+        let first = self.default_imports_count.min(self.imports.len());
+
+        self.imports[first..]
+            .iter()
+            .filter(|(_, symbol, _)| !used.contains(symbol))
+            .cloned()
+            .collect()
+    }
+
+    /// The local bindings in scope that no lookup resolved to, e.g. to warn about unused
+    /// variables. Like in Rust, a name that starts with an underscore marks a binding that is
+    /// meant to be unused, so those bindings are not included.
+    pub fn unused_idents(&self) -> Vec<(Ident, Symbol, Region)> {
+        let used = self.used_locals.borrow();
+
+        self.locals
+            .bindings_in_scope()
+            .filter(|(ident, symbol, _)| {
+                let is_used = used
+                    .get(symbol.ident_id().index())
+                    .map_or(false, |used| *used);
+
+                !ident.as_str().starts_with('_') && !is_used
+            })
+            .collect()
+    }
+
     fn has_imported(&self, ident: &str) -> Option<(Symbol, Region)> {
+        let ident = normalize(ident);
+
         for (import, shadow, original_region) in self.imports.iter() {
             if ident == import.as_str() {
                 return Some((*shadow, *original_region));
@@ -212,7 +1024,27 @@ impl Scope {
     }
 
     fn introduce_help(&mut self, ident: &str, region: Region) -> Result<Symbol, (Symbol, Region)> {
-        match self.scope_contains_ident(ident) {
+        // once introduced, the identifier is no longer pending
+        let mut index = 0;
+        while index < self.pending.len() {
+            if self.pending[index].0.as_str() == ident {
+                let removed = self.pending.remove(index);
+                self.log(Undo::PendingRemoved(index, removed));
+            } else {
+                index += 1;
+            }
+        }
+
+        let result = match self.scope_contains_ident(ident) {
+            ContainsIdent::InScope(original_symbol, _)
+                if self.inner_scope_depth == 0 && self.pre_introduced.remove(&original_symbol) =>
+            {
+                self.log(Undo::PreIntroduced(original_symbol, true));
+
+                // the definition of a top-level name that was introduced ahead of time. In an
+                // inner scope, the same name is a shadow of it instead.
+                Ok(original_symbol)
+            }
             ContainsIdent::InScope(original_symbol, original_region) => {
                 // the ident is already in scope; up to the caller how to handle that
                 // (usually it's shadowing, but it is valid to shadow ability members)
@@ -230,8 +1062,7 @@ impl Scope {
                     // other modules depend on the symbol having that IdentId
                     let symbol = Symbol::new(self.home, existing);
 
-                    self.locals.in_scope.set(existing.index(), true);
-                    self.locals.regions[existing.index()] = region;
+                    self.set_local(existing.index(), true, region);
 
                     Ok(symbol)
                 } else {
@@ -241,7 +1072,12 @@ impl Scope {
                     Ok(Symbol::new(self.home, ident_id))
                 }
             }
-        }
+        };
+
+        #[cfg(test)]
+        self.check_invariants();
+
+        result
     }
 
     /// Introduce a new ident to scope.
@@ -269,18 +1105,253 @@ impl Scope {
         match self.introduce_help(ident, region) {
             Ok(symbol) => Ok(symbol),
             Err((shadowed_symbol, original_region)) => {
+                self.note_shadowed(shadowed_symbol);
+
                 let shadow = Loc {
                     value: Ident::from(ident),
                     region,
                 };
                 let symbol = self.locals.scopeless_symbol(ident, region);
 
+                #[cfg(test)]
+                self.check_invariants();
+
                 Err((Loc::at(original_region, shadowed_symbol), shadow, symbol))
             }
         }
     }
 
-    /// Like [Self::introduce], but does not introduce a new symbol for the shadowing symbol.
+    /// Like [Self::introduce], but also tells whether the name is exposed by the module header,
+    /// i.e. whether this binding is part of the module's public interface.
+    #[allow(clippy::type_complexity)]
+    pub fn introduce_with_exposed(
+        &mut self,
+        ident: Ident,
+        region: Region,
+    ) -> Result<(Symbol, WasExposed), (Loc<Symbol>, Loc<Ident>, Symbol)> {
+        let symbol = self.introduce(ident, region)?;
+
+        // exposed identifiers keep the IdentId they got from the header
+        let was_exposed = symbol.ident_id().index() < self.exposed_ident_count;
+
+        Ok((symbol, WasExposed(was_exposed)))
+    }
+
+    /// The names in `exposed_ident_ids` that are not defined in this scope.
+    ///
+    /// A specialization of an ability member counts as a definition of its name.
+    pub fn verify_exposed(&self, exposed_ident_ids: &IdentIds) -> Vec<Ident> {
+        exposed_ident_ids
+            .ident_strs()
+            .filter(|(_, name)| !self.defines(name))
+            .map(|(_, name)| Ident::from(name))
+            .collect()
+    }
+
+    fn defines(&self, name: &str) -> bool {
+        let in_scope = matches!(self.locals.contains_ident(name), ContainsIdent::InScope(..));
+
+        in_scope
+            || self
+                .shadows
+                .values()
+                .any(|shadow| self.locals.ident_ids.get_name(shadow.value.ident_id()) == Some(name))
+    }
+
+    /// Introduce the names of a group of (mutually recursive) definitions at once, before any of
+    /// their bodies are canonicalized.
+    ///
+    /// Returns a symbol for every name, in order. A name that is defined twice within the group
+    /// is reported as a duplicate rather than as a shadow of itself. Names that conflict, with
+    /// the group or with the scope, get a symbol that is not in scope, like the shadows created
+    /// by [Self::introduce]. All conflicts are found before anything is introduced.
+    pub fn introduce_recursive_group(
+        &mut self,
+        names: &[(Ident, Region)],
+    ) -> (Vec<Symbol>, Vec<GroupConflict>) {
+        let mut conflicts = Vec::new();
+
+        let is_conflict: Vec<bool> = names
+            .iter()
+            .enumerate()
+            .map(|(index, (ident, region))| {
+                let earlier = names[..index].iter().find(|(other, _)| other == ident);
+
+                if let Some((_, original_region)) = earlier {
+                    conflicts.push(GroupConflict::Duplicate {
+                        original: Loc::at(*original_region, ident.clone()),
+                        duplicate: Loc::at(*region, ident.clone()),
+                    });
+
+                    true
+                } else if let ContainsIdent::InScope(symbol, original_region) =
+                    self.scope_contains_ident(ident.as_str())
+                {
+                    conflicts.push(GroupConflict::Shadows {
+                        original: Loc::at(original_region, symbol),
+                        shadow: Loc::at(*region, ident.clone()),
+                    });
+
+                    true
+                } else {
+                    false
+                }
+            })
+            .collect();
+
+        let symbols = names
+            .iter()
+            .zip(is_conflict)
+            .map(|((ident, region), is_conflict)| {
+                if is_conflict {
+                    self.scopeless_symbol(ident, *region)
+                } else {
+                    match self.introduce_help(ident.as_str(), *region) {
+                        Ok(symbol) => symbol,
+                        Err(_) => internal_error!("{:?} was checked not to be in scope", ident),
+                    }
+                }
+            })
+            .collect();
+
+        (symbols, conflicts)
+    }
+
+    /// Bring all top-level names of a module into scope before any of them is defined, so that
+    /// definitions can refer to the ones that come after them.
+    ///
+    /// Conflicts are reported like in [Self::introduce_recursive_group]. The next time one of
+    /// the names is introduced at the top level, which should be by its definition, the symbol
+    /// from this pre-introduction is returned instead of a shadowing error. Introducing one of
+    /// the names in an [inner scope](Self::inner_scope) is reported as shadowing it. Exposed names keep the
+    /// IdentId they got from the module header.
+    pub fn pre_introduce_top_level(
+        &mut self,
+        names: &[(Ident, Region)],
+    ) -> (Vec<Symbol>, Vec<GroupConflict>) {
+        let (symbols, conflicts) = self.introduce_recursive_group(names);
+
+        for symbol in symbols.iter() {
+            // conflicting names get a symbol that is not in scope
+            if self.locals.in_scope[symbol.ident_id().index()]
+                && !self.pre_introduced.insert(*symbol)
+            {
+                self.log(Undo::PreIntroduced(*symbol, false));
+            }
+        }
+
+        #[cfg(test)]
+        self.check_invariants();
+
+        (symbols, conflicts)
+    }
+
+    /// Introduce `ident` with a symbol from an earlier run, e.g. when rebuilding a scope from a
+    /// cache. Unlike [Self::introduce], this does not allocate a fresh IdentId, so the symbols
+    /// stay the same between runs.
+    ///
+    /// Returns Err if this would shadow an existing ident, like [Self::introduce].
+    pub fn introduce_with_symbol(
+        &mut self,
+        ident: Ident,
+        symbol: Symbol,
+        region: Region,
+    ) -> Result<(), ShadowError> {
+        if !self.is_home_symbol(symbol) {
+            internal_error!(
+                "{:?} cannot be introduced in module {:?}, it belongs to another module",
+                symbol,
+                self.home
+            );
+        }
+
+        self.bind(&ident, Binding::Local(symbol.ident_id()), region)
+            .map_err(|(original_symbol, original_region)| ShadowError {
+                original: Loc::at(original_region, original_symbol),
+                shadow: Loc::at(region, ident),
+            })
+    }
+
+    /// Bring `ident` into scope as `binding`, unless that would shadow a name that is already
+    /// in scope. Lookups prefer imports over locals, so an import only conflicts with another
+    /// import; a local conflicts with both.
+    fn bind(
+        &mut self,
+        ident: &Ident,
+        binding: Binding,
+        region: Region,
+    ) -> Result<(), (Symbol, Region)> {
+        let existing = match binding {
+            Binding::Import(_) => self.has_imported(ident.as_str()),
+            Binding::Local(_) => match self.scope_contains_ident(ident.as_str()) {
+                ContainsIdent::InScope(original_symbol, original_region) => {
+                    Some((original_symbol, original_region))
+                }
+                ContainsIdent::NotInScope(_) | ContainsIdent::NotPresent => None,
+            },
+        };
+
+        if let Some(original) = existing {
+            return Err(original);
+        }
+
+        match binding {
+            Binding::Local(ident_id) => {
+                let index = ident_id.index();
+
+                if index < self.locals.in_scope.len() {
+                    let was_in_scope = self.locals.in_scope[index];
+                    self.log(Undo::Local(index, was_in_scope, self.locals.regions[index]));
+                }
+
+                if self
+                    .locals
+                    .introduce_with_id(ident.as_str(), ident_id, region)
+                {
+                    self.log(Undo::GeneratedName(index));
+                }
+            }
+            Binding::Import(symbol) => {
+                let ident = match normalize(ident.as_str()) {
+                    Cow::Borrowed(_) => ident.clone(),
+                    Cow::Owned(normalized) => Ident::from(normalized),
+                };
+
+                self.imports.push((ident, symbol, region));
+            }
+        }
+
+        #[cfg(test)]
+        self.check_invariants();
+
+        Ok(())
+    }
+
+    /// Introduce `ident` with an IdentId that was allocated for it up front, e.g. when all
+    /// top-level names of a module are registered before their definitions are canonicalized.
+    /// No fresh IdentId is allocated, so the symbol is the one the caller already handed out.
+    ///
+    /// Returns Err if this would shadow an existing ident, like [Self::introduce].
+    pub fn introduce_with_id(
+        &mut self,
+        ident: Ident,
+        ident_id: IdentId,
+        region: Region,
+    ) -> Result<Symbol, ShadowError> {
+        debug_assert!(
+            ident_id.index() < self.locals.ident_ids.len(),
+            "{:?} was not allocated in module {:?}",
+            ident_id,
+            self.home
+        );
+
+        let symbol = Symbol::new(self.home, ident_id);
+        self.introduce_with_symbol(ident, symbol, region)?;
+
+        Ok(symbol)
+    }
+
+    /// Like [Self::introduce], but does not introduce a new symbol for the shadowing symbol.
     pub fn introduce_without_shadow_symbol(
         &mut self,
         ident: &Ident,
@@ -288,6 +1359,8 @@ impl Scope {
     ) -> Result<Symbol, (Symbol, Region, Loc<Ident>)> {
         match self.introduce_help(ident.as_str(), region) {
             Err((symbol, original_region)) => {
+                self.note_shadowed(symbol);
+
                 let shadow = Loc {
                     value: ident.clone(),
                     region,
@@ -298,6 +1371,26 @@ impl Scope {
         }
     }
 
+    /// The symbol that `ident` refers to if it is in scope (a local or an import), or else a
+    /// fresh symbol for it, which is introduced into the scope.
+    ///
+    /// Unlike [Self::introduce], a name that is already in scope is not a shadow: its symbol is
+    /// returned as is, and no problem is reported. Only use this for names that are implicitly
+    /// introduced where they are first used.
+    pub fn lookup_or_introduce(&mut self, ident: &Ident, region: Region) -> Symbol {
+        match self.introduce_help(ident.as_str(), region) {
+            Ok(symbol) | Err((symbol, _)) => symbol,
+        }
+    }
+
+    /// The ability that declares `member`, or `None` if it is not an ability member.
+    pub fn ability_of_member(&self, member: Symbol) -> Option<Symbol> {
+        self.abilities_store
+            .root_ability_members()
+            .get(&member)
+            .map(|data| data.parent_ability)
+    }
+
     /// Like [Self::introduce], but handles the case of when an ident matches an ability member
     /// name. In such cases a new symbol is created for the ident (since it's expected to be a
     /// specialization of the ability member), but the ident is not added to the ident->symbol map.
@@ -310,7 +1403,7 @@ impl Scope {
         pending_abilities_in_scope: &PendingAbilitiesInScope,
         ident: Ident,
         region: Region,
-    ) -> Result<(Symbol, Option<Symbol>), (Region, Loc<Ident>, Symbol)> {
+    ) -> Result<(Symbol, Option<Symbol>), (Loc<Symbol>, Loc<Ident>, Symbol)> {
         let ident = &ident;
 
         match self.introduce_help(ident.as_str(), region) {
@@ -329,11 +1422,15 @@ impl Scope {
                                 value: ident.clone(),
                                 region,
                             };
-                            Err((loc_original_shadow.region, shadow, shadow_symbol))
+                            Err((*loc_original_shadow, shadow, shadow_symbol))
                         }
                         None => {
                             self.shadows
                                 .insert(original_symbol, Loc::at(region, shadow_symbol));
+                            self.log(Undo::Shadow(original_symbol, None));
+
+                            #[cfg(test)]
+                            self.check_invariants();
 
                             Ok((shadow_symbol, Some(original_symbol)))
                         }
@@ -345,13 +1442,28 @@ impl Scope {
                         region,
                     };
 
-                    Err((original_region, shadow, shadow_symbol))
+                    Err((
+                        Loc::at(original_region, original_symbol),
+                        shadow,
+                        shadow_symbol,
+                    ))
                 }
             }
             Ok(symbol) => Ok((symbol, None)),
         }
     }
 
+    /// How to report that a new binding shadows `original`, the symbol that is already in scope
+    /// under that name. Shadowing a builtin that was imported by name, like `map` from
+    /// `imports [List.{ map }]`, is reported differently from shadowing a local binding.
+    pub fn shadow_kind(&self, original: Symbol) -> ShadowKind {
+        if original.is_builtin() && !self.is_home_symbol(original) {
+            ShadowKind::Builtin(original)
+        } else {
+            ShadowKind::Variable
+        }
+    }
+
     pub fn get_member_shadow(&self, ability_member: Symbol) -> Option<&Loc<Symbol>> {
         self.shadows.get(&ability_member)
     }
@@ -362,24 +1474,50 @@ impl Scope {
     /// but also in other places where we need to create a symbol and we don't have the right
     /// scope information yet. An identifier can be introduced later, and will use the same IdentId
     pub fn scopeless_symbol(&mut self, ident: &Ident, region: Region) -> Symbol {
-        self.locals.scopeless_symbol(ident.as_str(), region)
+        let symbol = self.locals.scopeless_symbol(ident.as_str(), region);
+
+        #[cfg(test)]
+        self.check_invariants();
+
+        symbol
     }
 
     /// Import a Symbol from another module into this module's top-level scope.
     ///
-    /// Returns Err if this would shadow an existing ident, including the
-    /// Symbol and Region of the ident we already had in scope under that name.
+    /// Returns Err if this would shadow an existing import, including the Symbol and Region of
+    /// the ident we already had in scope under that name. A local of the same name is not a
+    /// conflict: lookups of the name resolve to the import from then on.
     pub fn import(
         &mut self,
         ident: Ident,
         symbol: Symbol,
         region: Region,
     ) -> Result<(), (Symbol, Region)> {
-        if let Some((s, r)) = self.has_imported(ident.as_str()) {
+        self.bind(&ident, Binding::Import(symbol), region)
+    }
+
+    /// Import a type alias from another module into this module's top-level scope. Like
+    /// [`Scope::import`], but the alias also becomes available to [`Scope::lookup_alias`].
+    ///
+    /// Returns Err if this would shadow an existing ident, be it an import or a local (e.g. a
+    /// local alias of the same name). An imported opaque keeps its kind, but can still only be
+    /// wrapped or unwrapped in the module that defines it.
+    pub fn import_alias(
+        &mut self,
+        ident: Ident,
+        symbol: Symbol,
+        alias: Alias,
+        region: Region,
+    ) -> Result<(), (Symbol, Region)> {
+        if let Some((s, r)) = self.locals.has_in_scope(&ident) {
             return Err((s, r));
         }
 
-        self.imports.push((ident, symbol, region));
+        self.import(ident, symbol, region)?;
+        self.insert_alias(symbol, alias);
+
+        #[cfg(test)]
+        self.check_invariants();
 
         Ok(())
     }
@@ -393,14 +1531,175 @@ impl Scope {
         typ: Type,
         kind: AliasKind,
     ) {
+        self.check_shadows_builtin_alias(name, region);
+
         let alias = create_alias(name, region, vars, infer_ext_in_output_variables, typ, kind);
-        self.aliases.insert(name, alias);
+        self.insert_alias(name, alias);
+
+        #[cfg(test)]
+        self.check_invariants();
+    }
+
+    /// Register the alias of `from` under `to` as well, defined at `new_region`, without
+    /// recomputing its body. The alias of `from` stays as it is.
+    pub fn copy_alias(
+        &mut self,
+        from: Symbol,
+        to: Symbol,
+        new_region: Region,
+    ) -> Result<(), AliasNotFound> {
+        let mut alias = match self.aliases.get(&from) {
+            Some(alias) => alias.clone(),
+            None => return Err(AliasNotFound(from)),
+        };
+        alias.region = new_region;
+
+        self.check_shadows_builtin_alias(to, new_region);
+        self.insert_alias(to, alias);
+
+        #[cfg(test)]
+        self.check_invariants();
+
+        Ok(())
+    }
+
+    fn check_shadows_builtin_alias(&mut self, name: Symbol, region: Region) {
+        if !self.is_home_symbol(name) {
+            return;
+        }
+
+        let ident = match self.locals.ident_ids.get_name(name.ident_id()) {
+            Some(ident) => ident,
+            None => return,
+        };
+
+        if let Some(builtin) = Symbol::builtin_type_named(ident) {
+            if builtin != name {
+                self.shadowed_builtin_aliases.push(ShadowedBuiltinAlias {
+                    name: Ident::from(ident),
+                    builtin,
+                    region,
+                });
+                self.log(Undo::ShadowedBuiltinAliasPushed);
+            }
+        }
+    }
+
+    /// The aliases defined in this module that have the same name as a builtin type
+    pub fn shadowed_builtin_aliases(&self) -> &[ShadowedBuiltinAlias] {
+        &self.shadowed_builtin_aliases
+    }
+
+    /// The aliases that are defined in this module, as opposed to imported ones.
+    pub fn local_aliases(&self) -> impl Iterator<Item = (Symbol, &Alias)> {
+        self.aliases
+            .iter()
+            .filter(|(symbol, _)| self.is_home_symbol(**symbol))
+            .map(|(symbol, alias)| (*symbol, alias))
+    }
+
+    /// The aliases in scope that are defined within `region`, in order of definition.
+    pub fn aliases_in_region(&self, region: Region) -> Vec<(Symbol, &Alias)> {
+        self.aliases
+            .iter()
+            .filter(|(_, alias)| region.contains(&alias.region))
+            .map(|(symbol, alias)| (*symbol, alias))
+            .collect()
     }
 
     pub fn lookup_alias(&self, symbol: Symbol) -> Option<&Alias> {
         self.aliases.get(&symbol)
     }
 
+    pub fn aliases(&self) -> &VecMap<Symbol, Alias> {
+        &self.aliases
+    }
+
+    pub fn alias_count(&self) -> usize {
+        self.aliases.len()
+    }
+
+    /// For every alias in scope, the other aliases in scope that its type refers to, e.g. to
+    /// define aliases in dependency order. A recursive alias does not depend on itself.
+    pub fn alias_dependencies(&self) -> Vec<(Symbol, Vec<Symbol>)> {
+        self.aliases
+            .iter()
+            .map(|(symbol, alias)| {
+                let mut dependencies = alias.typ.symbols();
+                dependencies.retain(|s| s != symbol && self.aliases.contains_key(s));
+
+                (*symbol, dependencies)
+            })
+            .collect()
+    }
+
+    pub fn remove_alias(&mut self, symbol: Symbol) -> Option<Alias> {
+        let removed = self.aliases_mut().remove(&symbol).map(|(_, alias)| alias);
+
+        if let Some(alias) = &removed {
+            self.log(Undo::Alias(symbol, Some(alias.clone())));
+        }
+
+        removed
+    }
+
+    /// Changes whenever the aliases in scope may have changed, so that information derived from
+    /// them can be cached, and recomputed when it is stale.
+    pub fn aliases_generation(&self) -> u32 {
+        self.aliases_generation
+    }
+
+    fn aliases_mut(&mut self) -> &mut VecMap<Symbol, Alias> {
+        self.aliases_generation = self.aliases_generation.wrapping_add(1);
+
+        &mut self.aliases
+    }
+
+    fn insert_alias(&mut self, symbol: Symbol, alias: Alias) {
+        let old = self.aliases_mut().insert(symbol, alias);
+        self.log(Undo::Alias(symbol, old));
+    }
+
+    /// The type behind the alias `symbol`, with its type variables replaced by `args`.
+    pub fn instantiate_alias(
+        &self,
+        symbol: Symbol,
+        args: &[Type],
+    ) -> Result<Type, AliasInstantiationError> {
+        let alias = self
+            .aliases
+            .get(&symbol)
+            .ok_or(AliasInstantiationError::NotInScope(symbol))?;
+
+        if args.len() != alias.type_variables.len() {
+            return Err(AliasInstantiationError::WrongArity {
+                symbol,
+                expected: alias.type_variables.len(),
+                actual: args.len(),
+            });
+        }
+
+        let substitutions = alias
+            .type_variables
+            .iter()
+            .zip(args)
+            .map(|(loc_var, arg)| (loc_var.value.var, arg.clone()))
+            .collect();
+
+        let mut typ = alias.typ.clone();
+        typ.substitute(&substitutions);
+
+        Ok(typ)
+    }
+
+    /// Move the aliases out of the scope, e.g. into the module's output once canonicalization
+    /// is done. The scope is left without any aliases.
+    pub fn take_aliases(&mut self) -> VecMap<Symbol, Alias> {
+        self.invalidate_snapshots();
+
+        std::mem::take(self.aliases_mut())
+    }
+
     pub fn contains_alias(&mut self, name: Symbol) -> bool {
         self.aliases.contains_key(&name)
     }
@@ -414,416 +1713,3363 @@ impl Scope {
         // - abilities_store: ability definitions not allowed in inner scopes
         // - locals: everything introduced in the inner scope is marked as not in scope in the rollback
         // - aliases: stored in a VecMap, we just discard anything added in an inner scope
+        // - pending: same as aliases
         // - exposed_ident_count: unchanged
         // - home: unchanged
         let aliases_count = self.aliases.len();
+        let pending_count = self.pending.len();
         let locals_snapshot = self.locals.in_scope.len();
 
+        self.inner_scope_depth += 1;
         let result = f(self);
+        self.inner_scope_depth -= 1;
 
-        self.aliases.truncate(aliases_count);
+        while self.aliases.len() > aliases_count {
+            let last = self.aliases.len() - 1;
+            let (symbol, alias) = self.aliases_mut().swap_remove(last);
+            self.log(Undo::Alias(symbol, Some(alias)));
+        }
+        while self.pending.len() > pending_count {
+            let last = self.pending.len() - 1;
+            let removed = self.pending.remove(last);
+            self.log(Undo::PendingRemoved(last, removed));
+        }
 
         // anything added in the inner scope is no longer in scope now
         for i in locals_snapshot..self.locals.in_scope.len() {
-            self.locals.in_scope.set(i, false);
+            if self.locals.in_scope[i] {
+                self.set_local(i, false, self.locals.regions[i]);
+            }
         }
 
-        result
-    }
+        #[cfg(test)]
+        self.check_invariants();
 
-    pub fn register_debug_idents(&self) {
-        self.home.register_debug_idents(&self.locals.ident_ids)
+        result
     }
 
-    /// Generates a unique, new symbol like "$1" or "$5",
-    /// using the home module as the module_id.
+    /// Take a snapshot to later find what was added since with [Scope::changes_since], or to
+    /// go back to with [Scope::rollback_to].
     ///
-    /// This is used, for example, during canonicalization of an Expr::Closure
-    /// to generate a unique symbol to refer to that closure.
-    pub fn gen_unique_symbol(&mut self) -> Symbol {
-        Symbol::new(self.home, self.locals.gen_unique())
+    /// Until the snapshot is rolled back to or [committed](Scope::commit), the scope records
+    /// how to undo the changes made to it.
+    pub fn snapshot(&self) -> ScopeSnapshot {
+        let mut undo_log = self.undo_log.borrow_mut();
+        undo_log.open_snapshots += 1;
+
+        ScopeSnapshot {
+            ident_count: self.locals.ident_ids.len(),
+            imports_count: self.imports.len(),
+            undo_log_len: undo_log.entries.len(),
+            var_store: None,
+            scope_id: self.id,
+            generation: self.generation,
+        }
     }
-}
-
-pub fn create_alias(
-    name: Symbol,
-    region: Region,
-    vars: Vec<Loc<AliasVar>>,
-    infer_ext_in_output_variables: Vec<Variable>,
-    typ: Type,
-    kind: AliasKind,
-) -> Alias {
-    let roc_types::types::VariableDetail {
-        type_variables,
-        lambda_set_variables,
-        recursion_variables,
-    } = typ.variables_detail();
 
-    debug_assert!({
-        let mut hidden = type_variables;
+    /// Keep the changes made since `snapshot` was taken, and stop recording them if no other
+    /// snapshot is open.
+    pub fn commit(&mut self, snapshot: ScopeSnapshot) {
+        self.assert_open(&snapshot);
+        self.close_snapshot();
+    }
 
-        for var in (vars.iter().map(|lv| lv.value.var))
-            .chain(infer_ext_in_output_variables.iter().copied())
-        {
-            hidden.remove(&var);
-        }
+    /// Called when a snapshot is rolled back to or committed
+    fn close_snapshot(&mut self) {
+        let undo_log = self.undo_log.get_mut();
+        undo_log.open_snapshots -= 1;
 
-        if !hidden.is_empty() {
-            panic!(
-                "Found unbound type variables {:?} \n in type alias {:?} {:?} {:?} : {:?}",
-                hidden, name, &vars, &infer_ext_in_output_variables, &typ
-            )
+        if undo_log.open_snapshots == 0 {
+            undo_log.entries.clear();
         }
-
-        true
-    });
-
-    let lambda_set_variables: Vec<_> = lambda_set_variables
-        .into_iter()
-        .map(|v| roc_types::types::LambdaSet(Type::Variable(v)))
-        .collect();
-
-    Alias {
-        region,
-        type_variables: vars,
-        lambda_set_variables,
-        infer_ext_in_output_variables,
-        recursion_variables,
-        typ,
-        kind,
     }
-}
 
-#[derive(Debug)]
-enum ContainsIdent {
-    InScope(Symbol, Region),
-    NotInScope(IdentId),
-    NotPresent,
-}
+    fn assert_open(&self, snapshot: &ScopeSnapshot) {
+        debug_assert_eq!(
+            snapshot.scope_id, self.id,
+            "rolling back to a snapshot of a different scope"
+        );
+        debug_assert_eq!(
+            snapshot.generation, self.generation,
+            "rolling back to a snapshot that was invalidated by removing from the scope"
+        );
+        debug_assert!(
+            snapshot.imports_count <= self.imports.len()
+                && snapshot.undo_log_len <= self.undo_log.borrow().entries.len(),
+            "rolling back to a snapshot that was already rolled back past"
+        );
+    }
 
-#[derive(Clone, Debug)]
-pub struct ScopedIdentIds {
-    pub ident_ids: IdentIds,
-    in_scope: BitVec,
-    regions: Vec<Region>,
-    home: ModuleId,
-}
+    /// The counts in a snapshot are only meaningful while nothing that existed when it was
+    /// taken has been removed.
+    fn invalidate_snapshots(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
 
-impl ScopedIdentIds {
-    fn from_ident_ids(home: ModuleId, ident_ids: IdentIds) -> Self {
-        let capacity = ident_ids.len();
+    /// Record how to undo a change, if a snapshot is open
+    fn log(&self, undo: Undo) {
+        let mut undo_log = self.undo_log.borrow_mut();
 
-        Self {
-            in_scope: BitVec::repeat(false, capacity),
-            ident_ids,
-            regions: vec![Region::zero(); capacity],
-            home,
+        if undo_log.open_snapshots > 0 {
+            undo_log.entries.push(undo);
         }
     }
 
-    fn has_in_scope(&self, ident: &Ident) -> Option<(Symbol, Region)> {
-        match self.contains_ident(ident.as_str()) {
-            ContainsIdent::InScope(symbol, region) => Some((symbol, region)),
-            ContainsIdent::NotInScope(_) | ContainsIdent::NotPresent => None,
-        }
+    /// Bring the local with this index in or out of scope, and set its region
+    fn set_local(&mut self, index: usize, in_scope: bool, region: Region) {
+        let was_in_scope = self.locals.in_scope.replace(index, in_scope);
+        let old_region = std::mem::replace(&mut self.locals.regions[index], region);
+
+        self.log(Undo::Local(index, was_in_scope, old_region));
     }
 
-    fn contains_ident(&self, ident: &str) -> ContainsIdent {
-        use ContainsIdent::*;
+    fn undo(&mut self, undo: Undo) {
+        match undo {
+            Undo::Alias(symbol, Some(alias)) => {
+                self.aliases_mut().insert(symbol, alias);
+            }
+            Undo::Alias(symbol, None) => {
+                self.aliases_mut().remove(&symbol);
+            }
+            Undo::Shadow(member, Some(shadow)) => {
+                self.shadows.insert(member, shadow);
+            }
+            Undo::Shadow(member, None) => {
+                self.shadows.remove(&member);
+            }
+            Undo::Local(index, in_scope, region) => {
+                self.locals.in_scope.set(index, in_scope);
+                self.locals.regions[index] = region;
+            }
+            Undo::GeneratedName(index) => {
+                let generated = index.to_string();
+                self.locals.ident_ids.interner.update(index, &generated);
+            }
+            Undo::PendingPushed => {
+                self.pending.pop();
+            }
+            Undo::PendingRemoved(index, pending) => {
+                self.pending.insert(index, pending);
+            }
+            Undo::ExposedElsewhere(module, Some(exposed)) => {
+                self.exposed_elsewhere.insert(module, exposed);
+            }
+            Undo::ExposedElsewhere(module, None) => {
+                self.exposed_elsewhere.remove(&module);
+            }
+            Undo::ShadowedBuiltinAliasPushed => {
+                self.shadowed_builtin_aliases.pop();
+            }
+            Undo::Recovered(ident, Some(symbol)) => {
+                self.recovered.insert(ident, symbol);
+            }
+            Undo::Recovered(ident, None) => {
+                self.recovered.remove(&ident);
+            }
+            Undo::Deprecated(symbol, Some(note)) => {
+                self.deprecated.insert(symbol, note);
+            }
+            Undo::Deprecated(symbol, None) => {
+                self.deprecated.remove(&symbol);
+            }
+            Undo::ModuleAlias(alias, Some(module_id)) => {
+                self.module_aliases.insert(alias, module_id);
+            }
+            Undo::ModuleAlias(alias, None) => {
+                self.module_aliases.remove(&alias);
+            }
+            Undo::PreIntroduced(symbol, true) => {
+                self.pre_introduced.insert(symbol);
+            }
+            Undo::PreIntroduced(symbol, false) => {
+                self.pre_introduced.remove(&symbol);
+            }
+            Undo::UsedImport(symbol) => {
+                self.used_imports.get_mut().remove(&symbol);
+            }
+            Undo::UsedLocal(index) => {
+                self.used_locals.get_mut().set(index, false);
+            }
+            Undo::ShadowedImport(symbol) => {
+                self.shadowed_imports.remove(&symbol);
+            }
+        }
+    }
 
-        let mut result = NotPresent;
+    /// Like [Scope::snapshot], but rolling back to it with [Scope::rollback_to_with_var_store]
+    /// also rolls `var_store` back, so that the variables of e.g. aliases that are discarded can
+    /// be handed out again.
+    pub fn snapshot_with_var_store(&self, var_store: &VarStore) -> ScopeSnapshot {
+        ScopeSnapshot {
+            var_store: Some(var_store.snapshot()),
+            ..self.snapshot()
+        }
+    }
 
-        for ident_id in self.ident_ids.get_id_many(ident) {
-            let index = ident_id.index();
-            if self.in_scope[index] {
-                return InScope(Symbol::new(self.home, ident_id), self.regions[index]);
-            } else {
-                result = NotInScope(ident_id)
-            }
+    /// Discard the bindings, imports, aliases and ability member shadows that were added since
+    /// `snapshot` was taken. Identifiers created since stay in the interner, but are no longer
+    /// in scope. Everything else that changed since, like aliases that were overwritten, the
+    /// pending and deprecated names, or the lookups that marked names as used, is restored.
+    ///
+    /// A snapshot taken with [Scope::snapshot_with_var_store] is rolled back to with
+    /// [Scope::rollback_to_with_var_store] instead.
+    pub fn rollback_to(&mut self, snapshot: ScopeSnapshot) {
+        debug_assert!(
+            snapshot.var_store.is_none(),
+            "rolling back to a snapshot with a var store, but not the var store"
+        );
+
+        self.rollback_help(snapshot);
+    }
+
+    /// Like [Scope::rollback_to], but for a snapshot taken with
+    /// [Scope::snapshot_with_var_store], and `var_store` is rolled back too. The variables
+    /// created since must no longer be in use, e.g. by expressions canonicalized in the
+    /// discarded scope.
+    pub fn rollback_to_with_var_store(
+        &mut self,
+        snapshot: ScopeSnapshot,
+        var_store: &mut VarStore,
+    ) {
+        let var_store_snapshot = match snapshot.var_store {
+            Some(var_store_snapshot) => var_store_snapshot,
+            None => internal_error!("the scope snapshot was taken without a var store"),
+        };
+
+        self.rollback_help(snapshot);
+
+        debug_assert!(
+            self.aliases.iter().all(|(_, alias)| {
+                let vars = alias.typ.variables();
+                !vars.iter().any(|var| var_store_snapshot.is_newer(*var))
+            }),
+            "an alias that is kept uses a variable that is rolled back"
+        );
+
+        var_store.rollback_to(var_store_snapshot);
+    }
+
+    fn rollback_help(&mut self, snapshot: ScopeSnapshot) {
+        self.assert_open(&snapshot);
+
+        let entries = self
+            .undo_log
+            .get_mut()
+            .entries
+            .split_off(snapshot.undo_log_len);
+
+        for undo in entries.into_iter().rev() {
+            self.undo(undo);
         }
 
-        result
+        self.close_snapshot();
+
+        // identifiers created since keep the region they were introduced at
+        for index in snapshot.ident_count..self.locals.in_scope.len() {
+            self.locals.in_scope.set(index, false);
+        }
+
+        self.imports.truncate(snapshot.imports_count);
+
+        #[cfg(test)]
+        self.check_invariants();
     }
 
-    fn idents_in_scope(&self) -> impl Iterator<Item = Ident> + '_ {
-        self.ident_ids
-            .ident_strs()
-            .zip(self.in_scope.iter())
-            .filter_map(|((_, string), keep)| {
-                if *keep {
-                    Some(Ident::from(string))
-                } else {
-                    None
+    /// Everything that was added to this scope since `snapshot` was taken from it.
+    ///
+    /// Bindings that went out of scope again in the meantime, e.g. in an inner scope, are not
+    /// part of the delta.
+    pub fn changes_since(&self, snapshot: &ScopeSnapshot) -> ScopeDelta {
+        let undo_log = self.undo_log.borrow();
+        let changes = &undo_log.entries[snapshot.undo_log_len..];
+
+        // the first change to a local holds whether it was in scope at the snapshot
+        let mut locals_at_snapshot = MutMap::default();
+        let mut changed_aliases = MutSet::default();
+        let mut changed_shadows = MutSet::default();
+
+        for undo in changes {
+            match undo {
+                Undo::Local(index, in_scope, _) => {
+                    locals_at_snapshot.entry(*index).or_insert(*in_scope);
+                }
+                Undo::Alias(symbol, _) => {
+                    changed_aliases.insert(*symbol);
+                }
+                Undo::Shadow(member, _) => {
+                    changed_shadows.insert(*member);
                 }
+                _ => {}
+            }
+        }
+
+        let was_in_scope = |index: usize| {
+            index < snapshot.ident_count
+                && *locals_at_snapshot
+                    .get(&index)
+                    .unwrap_or(&self.locals.in_scope[index])
+        };
+
+        let introduced: Vec<_> = self
+            .locals
+            .bindings_in_scope()
+            .filter(|(_, symbol, _)| !was_in_scope(symbol.ident_id().index()))
+            .collect();
+
+        let imports = self.imports[snapshot.imports_count..].to_vec();
+
+        // what a name resolved to at the time of the snapshot
+        let resolved_at_snapshot = |name: &Ident| {
+            let imported = self.imports[..snapshot.imports_count]
+                .iter()
+                .find(|(ident, _, _)| ident == name)
+                .map(|(_, symbol, _)| *symbol);
+
+            imported.or_else(|| {
+                self.locals
+                    .ident_ids
+                    .get_id_many(name.as_str())
+                    .find(|ident_id| was_in_scope(ident_id.index()))
+                    .map(|ident_id| Symbol::new(self.home, ident_id))
+            })
+        };
+
+        let overwritten = introduced
+            .iter()
+            .chain(imports.iter())
+            .filter_map(|(ident, symbol, _)| match resolved_at_snapshot(ident) {
+                Some(old) if old != *symbol => Some((old, *symbol)),
+                _ => None,
             })
+            .collect();
+
+        ScopeDelta {
+            first_ident_index: snapshot.ident_count,
+            new_idents: self
+                .locals
+                .ident_ids
+                .ident_strs()
+                .skip(snapshot.ident_count)
+                .map(|(_, string)| {
+                    // valid names can't start with a digit, only generated ones do
+                    if string.starts_with(|c: char| c.is_ascii_digit()) {
+                        None
+                    } else {
+                        Some(Ident::from(string))
+                    }
+                })
+                .collect(),
+            introduced,
+            imports,
+            aliases: (self.aliases.iter())
+                .filter(|(symbol, _)| changed_aliases.contains(*symbol))
+                .map(|(symbol, alias)| (*symbol, alias.clone()))
+                .collect(),
+            specializations: (self.shadows.iter())
+                .filter(|(member, _)| changed_shadows.contains(*member))
+                .map(|(member, shadow)| (*member, *shadow))
+                .collect(),
+            overwritten,
+        }
+    }
+
+    /// Replay the changes of `delta` onto this scope.
+    ///
+    /// This scope must be in the state that the delta's snapshot was taken in, for instance
+    /// because the delta was computed on a clone of it.
+    pub fn apply_delta(&mut self, delta: &ScopeDelta) {
+        if self.locals.ident_ids.len() != delta.first_ident_index {
+            internal_error!(
+                "a scope delta starting at ident {} is applied to a scope with {} idents",
+                delta.first_ident_index,
+                self.locals.ident_ids.len()
+            );
+        }
+
+        for ident in delta.new_idents.iter() {
+            match ident {
+                Some(ident) => {
+                    self.locals.scopeless_symbol(ident.as_str(), Region::zero());
+                }
+                None => {
+                    self.locals.gen_unique();
+                }
+            }
+        }
+
+        for (_, symbol, region) in delta.introduced.iter() {
+            self.set_local(symbol.ident_id().index(), true, *region);
+        }
+
+        self.imports.extend(delta.imports.iter().cloned());
+
+        for (symbol, alias) in delta.aliases.iter() {
+            self.insert_alias(*symbol, alias.clone());
+        }
+
+        for (member, shadow) in delta.specializations.iter() {
+            let old = self.shadows.insert(*member, *shadow);
+            self.log(Undo::Shadow(*member, old));
+        }
+
+        #[cfg(test)]
+        self.check_invariants();
+    }
+
+    pub fn register_debug_idents(&self) {
+        self.home.register_debug_idents(&self.locals.ident_ids)
+    }
+
+    /// Generates a unique, new symbol like "$1" or "$5",
+    /// using the home module as the module_id.
+    ///
+    /// This is used, for example, during canonicalization of an Expr::Closure
+    /// to generate a unique symbol to refer to that closure.
+    pub fn gen_unique_symbol(&mut self) -> Symbol {
+        let symbol = Symbol::new(self.home, self.locals.gen_unique());
+
+        #[cfg(test)]
+        self.check_invariants();
+
+        symbol
+    }
+
+    /// Panics if the internal bookkeeping of the scope is inconsistent.
+    ///
+    /// This runs after every mutation in this crate's tests, so that a corrupted scope is
+    /// reported by the operation that corrupted it, rather than phases later. It scans the whole
+    /// scope, which would make canonicalizing large modules quadratic, so other builds skip it.
+    #[cfg(test)]
+    pub fn check_invariants(&self) {
+        self.locals.check_invariants();
+
+        let undo_log = self.undo_log.borrow();
+        assert!(
+            undo_log.open_snapshots > 0 || undo_log.entries.is_empty(),
+            "changes are recorded while no snapshot is open"
+        );
+
+        let is_local = |symbol: Symbol| {
+            self.is_home_symbol(symbol) && symbol.ident_id().index() < self.locals.ident_ids.len()
+        };
+
+        for (member, shadow) in self.shadows.iter() {
+            assert!(
+                is_local(shadow.value),
+                "the shadow {:?} of {:?} is not an identifier of this module",
+                shadow.value,
+                member
+            );
+        }
+
+        for symbol in self.pre_introduced.iter() {
+            assert!(
+                is_local(*symbol),
+                "the pre-introduced {:?} is not an identifier of this module",
+                symbol
+            );
+        }
+
+        for symbol in self.aliases.keys() {
+            assert!(
+                !self.is_home_symbol(*symbol) || is_local(*symbol),
+                "the alias {:?} is not an identifier of this module",
+                symbol
+            );
+        }
+    }
+}
+
+/// Builds a [Scope] with a known set of imports and aliases, e.g. for tests or for a module
+/// with a fixed prelude.
+///
+/// All conflicts between the imports and aliases are reported by [ScopeBuilder::build] at once.
+#[derive(Debug)]
+pub struct ScopeBuilder {
+    home: ModuleId,
+    builtins: bool,
+    imports: Vec<(Ident, Symbol, Region)>,
+    aliases: Vec<BuilderAlias>,
+}
+
+#[derive(Debug)]
+struct BuilderAlias {
+    ident: Ident,
+    region: Region,
+    vars: Vec<Loc<AliasVar>>,
+    typ: Type,
+    kind: AliasKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AliasInstantiationError {
+    /// There is no alias with this symbol in scope
+    NotInScope(Symbol),
+    /// The alias was given a different number of type arguments than it has type variables
+    WrongArity {
+        symbol: Symbol,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScopeBuildError {
+    /// The name was imported before
+    DuplicateImport {
+        original: Loc<Symbol>,
+        shadow: Loc<Ident>,
+    },
+    /// The name of the alias is already in scope
+    AliasShadows {
+        original: Loc<Symbol>,
+        shadow: Loc<Ident>,
+    },
+}
+
+impl ScopeBuilder {
+    pub fn new(home: ModuleId) -> Self {
+        Self {
+            home,
+            builtins: false,
+            imports: Vec::new(),
+            aliases: Vec::new(),
+        }
+    }
+
+    /// Start from the default imports of [Scope::new] rather than from an empty scope.
+    pub fn with_builtins(mut self) -> Self {
+        self.builtins = true;
+        self
+    }
+
+    pub fn import(mut self, ident: Ident, symbol: Symbol, region: Region) -> Self {
+        self.imports.push((ident, symbol, region));
+        self
+    }
+
+    /// Introduce `ident` in the home module, and define it as an alias.
+    pub fn alias(
+        mut self,
+        ident: Ident,
+        region: Region,
+        vars: Vec<Loc<AliasVar>>,
+        typ: Type,
+        kind: AliasKind,
+    ) -> Self {
+        self.aliases.push(BuilderAlias {
+            ident,
+            region,
+            vars,
+            typ,
+            kind,
+        });
+        self
+    }
+
+    pub fn build(self) -> Result<Scope, Vec<ScopeBuildError>> {
+        let mut scope = if self.builtins {
+            Scope::new(
+                self.home,
+                IdentIds::default(),
+                PendingAbilitiesStore::default(),
+            )
+        } else {
+            Scope::new_empty(self.home)
+        };
+
+        let mut errors = Vec::new();
+
+        scope.aliases.reserve(self.aliases.len());
+
+        for (ident, symbol, region) in self.imports {
+            if let Err((original_symbol, original_region)) =
+                scope.import(ident.clone(), symbol, region)
+            {
+                errors.push(ScopeBuildError::DuplicateImport {
+                    original: Loc::at(original_region, original_symbol),
+                    shadow: Loc::at(region, ident),
+                });
+            }
+        }
+
+        for BuilderAlias {
+            ident,
+            region,
+            vars,
+            typ,
+            kind,
+        } in self.aliases
+        {
+            match scope.introduce_without_shadow_symbol(&ident, region) {
+                Ok(symbol) => scope.add_alias(symbol, region, vars, vec![], typ, kind),
+                Err((original_symbol, original_region, shadow)) => {
+                    errors.push(ScopeBuildError::AliasShadows {
+                        original: Loc::at(original_region, original_symbol),
+                        shadow,
+                    });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(scope)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Identifiers are compared in Unicode normalization form C, so that e.g. an `é` written as a
+/// single character and one written as `e` followed by a combining accent are the same name.
+///
+/// Does not allocate for ASCII identifiers, or ones that are in that form already.
+fn normalize(ident: &str) -> Cow<str> {
+    if ident.is_ascii() || is_nfc_quick(ident.chars()) == IsNormalized::Yes {
+        Cow::Borrowed(ident)
+    } else {
+        Cow::Owned(ident.nfc().collect())
+    }
+}
+
+pub fn create_alias(
+    name: Symbol,
+    region: Region,
+    vars: Vec<Loc<AliasVar>>,
+    infer_ext_in_output_variables: Vec<Variable>,
+    typ: Type,
+    kind: AliasKind,
+) -> Alias {
+    let roc_types::types::VariableDetail {
+        type_variables,
+        lambda_set_variables,
+        recursion_variables,
+    } = typ.variables_detail();
+
+    debug_assert!({
+        let bound: VecSet<Variable> = (vars.iter().map(|lv| lv.value.var))
+            .chain(infer_ext_in_output_variables.iter().copied())
+            .collect();
+
+        let hidden: Vec<_> = type_variables
+            .iter()
+            .filter(|var| !bound.contains(var))
+            .collect();
+
+        if !hidden.is_empty() {
+            panic!(
+                "Found unbound type variables {:?} \n in type alias {:?} {:?} {:?} : {:?}",
+                hidden, name, &vars, &infer_ext_in_output_variables, &typ
+            )
+        }
+
+        true
+    });
+
+    let lambda_set_variables: Vec<_> = lambda_set_variables
+        .into_iter()
+        .map(|v| roc_types::types::LambdaSet(Type::Variable(v)))
+        .collect();
+
+    Alias {
+        region,
+        type_variables: vars,
+        lambda_set_variables,
+        infer_ext_in_output_variables,
+        recursion_variables,
+        typ,
+        kind,
     }
+}
+
+#[derive(Debug)]
+enum ContainsIdent {
+    InScope(Symbol, Region),
+    NotInScope(IdentId),
+    NotPresent,
+}
+
+#[derive(Clone, Debug)]
+pub struct ScopedIdentIds {
+    pub ident_ids: IdentIds,
+    in_scope: BitVec,
+    regions: Vec<Region>,
+    home: ModuleId,
+}
+
+impl ScopedIdentIds {
+    fn from_ident_ids(home: ModuleId, ident_ids: IdentIds) -> Self {
+        let capacity = ident_ids.len();
+
+        Self {
+            in_scope: BitVec::repeat(false, capacity),
+            ident_ids,
+            regions: vec![Region::zero(); capacity],
+            home,
+        }
+    }
+
+    #[cfg(test)]
+    fn check_invariants(&self) {
+        #[cfg(debug_assertions)]
+        self.ident_ids.interner.check_invariants();
+
+        assert_eq!(
+            self.in_scope.len(),
+            self.ident_ids.len(),
+            "in_scope and ident_ids are out of sync"
+        );
+        assert_eq!(
+            self.regions.len(),
+            self.ident_ids.len(),
+            "regions and ident_ids are out of sync"
+        );
+
+        let mut seen = roc_collections::MutSet::default();
+
+        for ident in self.idents_in_scope() {
+            assert!(
+                !seen.contains(&ident),
+                "{:?} is in scope more than once",
+                ident
+            );
+
+            seen.insert(ident);
+        }
+    }
+
+    fn has_in_scope(&self, ident: &Ident) -> Option<(Symbol, Region)> {
+        match self.contains_ident(ident.as_str()) {
+            ContainsIdent::InScope(symbol, region) => Some((symbol, region)),
+            ContainsIdent::NotInScope(_) | ContainsIdent::NotPresent => None,
+        }
+    }
+
+    fn contains_ident(&self, ident: &str) -> ContainsIdent {
+        use ContainsIdent::*;
+
+        let ident = normalize(ident);
+        let mut result = NotPresent;
+
+        for ident_id in self.ident_ids.get_id_many(&ident) {
+            let index = ident_id.index();
+            if self.in_scope[index] {
+                return InScope(Symbol::new(self.home, ident_id), self.regions[index]);
+            } else {
+                result = NotInScope(ident_id)
+            }
+        }
+
+        result
+    }
+
+    fn idents_in_scope(&self) -> impl Iterator<Item = Ident> + '_ {
+        self.ident_ids
+            .ident_strs()
+            .zip(self.in_scope.iter())
+            .filter_map(|((_, string), keep)| {
+                if *keep {
+                    Some(Ident::from(string))
+                } else {
+                    None
+                }
+            })
+    }
+
+    fn bindings_in_scope(&self) -> impl Iterator<Item = (Ident, Symbol, Region)> + '_ {
+        self.ident_ids
+            .ident_strs()
+            .zip(self.in_scope.iter())
+            .filter_map(|((ident_id, string), keep)| {
+                if *keep {
+                    Some((
+                        Ident::from(string),
+                        Symbol::new(self.home, ident_id),
+                        self.regions[ident_id.index()],
+                    ))
+                } else {
+                    None
+                }
+            })
+    }
+
+    fn introduce_into_scope(&mut self, ident_name: &str, region: Region) -> IdentId {
+        let id = self.ident_ids.add_str(&normalize(ident_name));
+
+        debug_assert_eq!(id.index(), self.in_scope.len());
+        debug_assert_eq!(id.index(), self.regions.len());
+
+        self.in_scope.push(true);
+        self.regions.push(region);
+
+        id
+    }
+
+    /// Introduce `ident_name` with an IdentId that was handed out before, e.g. in an earlier
+    /// run of the compiler. Missing IdentIds below it are filled with generated names.
+    ///
+    /// Returns whether the IdentId had a generated name, which is now replaced by `ident_name`.
+    fn introduce_with_id(&mut self, ident_name: &str, id: IdentId, region: Region) -> bool {
+        let index = id.index();
+        let ident_name = normalize(ident_name);
+
+        while self.ident_ids.len() <= index {
+            self.gen_unique();
+        }
+
+        let renamed = match self.ident_ids.get_name(id) {
+            Some(existing) if existing == ident_name => false,
+            Some(existing) if existing == index.to_string() => {
+                // a generated name; the name of this IdentId was not known until now
+                self.ident_ids.interner.update(index, &ident_name);
+
+                true
+            }
+            existing => internal_error!(
+                "IdentId {} is {:?}, so it cannot be used for {:?}",
+                index,
+                existing,
+                ident_name
+            ),
+        };
+
+        self.in_scope.set(index, true);
+        self.regions[index] = region;
+
+        renamed
+    }
+
+    fn introduce_into_scope_duplicate(&mut self, existing: IdentId, region: Region) -> IdentId {
+        let id = self.ident_ids.duplicate_ident(existing);
+
+        debug_assert_eq!(id.index(), self.in_scope.len());
+        debug_assert_eq!(id.index(), self.regions.len());
+
+        self.in_scope.push(true);
+        self.regions.push(region);
+
+        id
+    }
+
+    /// Adds an IdentId, but does not introduce it to the scope
+    fn scopeless_symbol(&mut self, ident_name: &str, region: Region) -> Symbol {
+        let id = self.ident_ids.add_str(&normalize(ident_name));
+
+        debug_assert_eq!(id.index(), self.in_scope.len());
+        debug_assert_eq!(id.index(), self.regions.len());
+
+        self.in_scope.push(false);
+        self.regions.push(region);
+
+        Symbol::new(self.home, id)
+    }
+
+    fn gen_unique(&mut self) -> IdentId {
+        let id = self.ident_ids.gen_unique();
+
+        debug_assert_eq!(id.index(), self.in_scope.len());
+        debug_assert_eq!(id.index(), self.regions.len());
+
+        self.in_scope.push(false);
+        self.regions.push(Region::zero());
+
+        id
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::abilities::{AbilityMemberData, PendingMemberType};
+    use roc_collections::SendMap;
+    use roc_module::symbol::ModuleIds;
+    use roc_region::all::Position;
+    use roc_types::types::{AliasCommon, RecordField, TypeExtension};
+
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn scope_contains_introduced() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = Region::zero();
+        let ident = Ident::from("mezolit");
+
+        assert!(scope.lookup(&ident, region).is_err());
+
+        assert!(scope.introduce(ident.clone(), region).is_ok());
+
+        assert!(scope.lookup(&ident, region).is_ok());
+    }
+
+    #[test]
+    fn second_introduce_shadows() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region1 = Region::from_pos(Position { offset: 10 });
+        let region2 = Region::from_pos(Position { offset: 20 });
+        let ident = Ident::from("mezolit");
+
+        assert!(scope.lookup(&ident, Region::zero()).is_err());
+
+        let first = scope.introduce(ident.clone(), region1).unwrap();
+        let (original, _ident, shadow_symbol) =
+            scope.introduce(ident.clone(), region2).unwrap_err();
+
+        scope.register_debug_idents();
+
+        assert_ne!(first, shadow_symbol);
+        assert_eq!(original.region, region1);
+
+        let lookup = scope.lookup(&ident, Region::zero()).unwrap();
+
+        assert_eq!(first, lookup);
+    }
+
+    #[test]
+    fn inner_scope_does_not_influence_outer() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = Region::zero();
+        let ident = Ident::from("uránia");
+
+        assert!(scope.lookup(&ident, region).is_err());
+
+        scope.inner_scope(|inner| {
+            assert!(inner.introduce(ident.clone(), region).is_ok());
+        });
+
+        assert!(scope.lookup(&ident, region).is_err());
+    }
+
+    #[test]
+    fn default_idents_in_scope() {
+        let _register_module_debug_names = ModuleIds::default();
+        let scope = Scope::new(
+            ModuleId::ATTR,
+            IdentIds::default(),
+            PendingAbilitiesStore::default(),
+        );
+
+        let idents: Vec<_> = scope.idents_in_scope().collect();
+
+        assert_eq!(
+            &idents,
+            &[
+                Ident::from("Str"),
+                Ident::from("List"),
+                Ident::from("Box"),
+                Ident::from("Ok"),
+                Ident::from("Err"),
+            ]
+        );
+    }
+
+    #[test]
+    fn idents_with_inner_scope() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = Region::zero();
+
+        let ident1 = Ident::from("uránia");
+        let ident2 = Ident::from("malmok");
+        let ident3 = Ident::from("Járnak");
+
+        scope.introduce(ident1.clone(), region).unwrap();
+        scope.introduce(ident2.clone(), region).unwrap();
+        scope.introduce(ident3.clone(), region).unwrap();
+
+        let idents: Vec<_> = scope.idents_in_scope().collect();
+
+        assert_eq!(&idents, &[ident1.clone(), ident2.clone(), ident3.clone(),]);
+
+        scope.inner_scope(|inner| {
+            let ident4 = Ident::from("Ångström");
+            let ident5 = Ident::from("Sirály");
+
+            inner.introduce(ident4.clone(), region).unwrap();
+            inner.introduce(ident5.clone(), region).unwrap();
+
+            let idents: Vec<_> = inner.idents_in_scope().collect();
+
+            assert_eq!(
+                &idents,
+                &[
+                    ident1.clone(),
+                    ident2.clone(),
+                    ident3.clone(),
+                    ident4,
+                    ident5
+                ]
+            );
+        });
+
+        let idents: Vec<_> = scope.idents_in_scope().collect();
+
+        assert_eq!(&idents, &[ident1, ident2, ident3,]);
+    }
+
+    #[test]
+    fn empty_scope_has_no_idents() {
+        let _register_module_debug_names = ModuleIds::default();
+        let scope = Scope::new_empty(ModuleId::ATTR);
+
+        assert_eq!(scope.num_idents(), 0);
+        assert_eq!(scope.idents_in_scope().count(), 0);
+    }
+
+    #[test]
+    fn empty_scope_does_not_know_builtins() {
+        let _register_module_debug_names = ModuleIds::default();
+        let scope = Scope::new_empty(ModuleId::ATTR);
+
+        for name in ["Str", "List", "Box", "Ok", "Err"] {
+            assert!(scope.lookup(&Ident::from(name), Region::zero()).is_err());
+        }
+    }
+
+    #[test]
+    fn lookup_of_pending_ident() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let usage_region = Region::from_pos(Position { offset: 10 });
+        let definition_region = Region::from_pos(Position { offset: 20 });
+        let ident = Ident::from("later");
+
+        scope.register_pending(ident.clone(), definition_region);
+
+        match scope.lookup(&ident, usage_region) {
+            Err(RuntimeError::UsedBeforeDefinition {
+                usage,
+                definition_region: region,
+            }) => {
+                assert_eq!(usage.value, ident);
+                assert_eq!(usage.region, usage_region);
+                assert_eq!(region, definition_region);
+            }
+            other => panic!("expected UsedBeforeDefinition, got {:?}", other),
+        }
+
+        let symbol = scope.introduce(ident.clone(), definition_region).unwrap();
+
+        assert_eq!(scope.lookup(&ident, usage_region), Ok(symbol));
+    }
+
+    #[test]
+    fn lookup_of_unknown_ident_is_not_pending() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        scope.register_pending(Ident::from("later"), Region::zero());
+
+        assert!(matches!(
+            scope.lookup(&Ident::from("unknown"), Region::zero()),
+            Err(RuntimeError::LookupNotInScope(..))
+        ));
+    }
+
+    fn exposed_idents(names: &[&str]) -> Vec<Ident> {
+        names.iter().map(|name| Ident::from(*name)).collect()
+    }
+
+    fn exposing(names: &[&str]) -> IdentIds {
+        let mut ident_ids = IdentIds::default();
+
+        for name in names {
+            ident_ids.add_str(name);
+        }
+
+        ident_ids
+    }
+
+    #[test]
+    fn lookup_exposed_by_one_import() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        scope.register_module_exposed(ModuleId::LIST, "List", exposed_idents(&["map", "walk"]));
+        scope.register_module_exposed(ModuleId::STR, "Str", exposed_idents(&["concat"]));
+
+        match scope.lookup(&Ident::from("map"), Region::zero()) {
+            Err(RuntimeError::LookupNotInScopeButExposed { usage, exposed_by }) => {
+                assert_eq!(usage.value, Ident::from("map"));
+                assert_eq!(exposed_by, vec![ModuleName::from("List")]);
+            }
+            other => panic!("expected LookupNotInScopeButExposed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn lookup_exposed_by_two_imports() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        scope.register_module_exposed(ModuleId::LIST, "List", exposed_idents(&["map", "len"]));
+        scope.register_module_exposed(ModuleId::DICT, "Dict", exposed_idents(&["len"]));
+
+        match scope.lookup(&Ident::from("len"), Region::zero()) {
+            Err(RuntimeError::LookupNotInScopeButExposed { exposed_by, .. }) => {
+                assert_eq!(
+                    exposed_by,
+                    vec![ModuleName::from("List"), ModuleName::from("Dict")]
+                );
+            }
+            other => panic!("expected LookupNotInScopeButExposed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn lookup_exposed_by_no_import() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        scope.register_module_exposed(ModuleId::LIST, "List", exposed_idents(&["map"]));
+
+        assert!(matches!(
+            scope.lookup(&Ident::from("mapp"), Region::zero()),
+            Err(RuntimeError::LookupNotInScope(..))
+        ));
+    }
+
+    #[test]
+    fn iter_bindings_yields_each_binding_once() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region1 = Region::from_pos(Position { offset: 10 });
+        let region2 = Region::from_pos(Position { offset: 20 });
+        let region3 = Region::from_pos(Position { offset: 30 });
+
+        scope
+            .import(Ident::from("product"), Symbol::LIST_PRODUCT, region1)
+            .unwrap();
+        let first = scope.introduce(Ident::from("first"), region2).unwrap();
+
+        // neither a scopeless symbol nor a rejected shadow is a binding
+        scope.scopeless_symbol(&Ident::from("guard"), region3);
+        let _ = scope.introduce(Ident::from("first"), region3).unwrap_err();
+
+        let second = scope.introduce(Ident::from("second"), region3).unwrap();
+
+        let bindings: Vec<_> = scope.iter_bindings().collect();
+
+        assert_eq!(
+            bindings,
+            vec![
+                (Ident::from("product"), Symbol::LIST_PRODUCT, region1),
+                (Ident::from("first"), first, region2),
+                (Ident::from("second"), second, region3),
+            ]
+        );
+    }
+
+    #[test]
+    fn import_is_in_scope() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let ident = Ident::from("product");
+        let symbol = Symbol::LIST_PRODUCT;
+        let region = Region::zero();
+
+        assert!(scope.lookup(&ident, region).is_err());
+
+        assert!(scope.import(ident.clone(), symbol, region).is_ok());
+
+        assert!(scope.lookup(&ident, region).is_ok());
+
+        assert!(scope.idents_in_scope().any(|x| x == ident));
+    }
+
+    #[test]
+    fn shadow_of_import() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let ident = Ident::from("product");
+        let symbol = Symbol::LIST_PRODUCT;
+
+        let region1 = Region::from_pos(Position { offset: 10 });
+        let region2 = Region::from_pos(Position { offset: 20 });
+
+        scope.import(ident.clone(), symbol, region1).unwrap();
+
+        let (original, _ident, shadow_symbol) =
+            scope.introduce(ident.clone(), region2).unwrap_err();
+
+        scope.register_debug_idents();
+
+        assert_ne!(symbol, shadow_symbol);
+        assert_eq!(original.region, region1);
+
+        let lookup = scope.lookup(&ident, Region::zero()).unwrap();
+
+        assert_eq!(symbol, lookup);
+    }
+
+    #[test]
+    fn alias_shadowing_builtin_alias() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let result_region = Region::from_pos(Position { offset: 10 });
+        let dict_region = Region::from_pos(Position { offset: 20 });
+
+        let mut add_local_alias = |name: &str, region| {
+            let ident = Ident::from(name);
+            let symbol = scope.introduce(ident, region).unwrap();
+
+            scope.add_alias(
+                symbol,
+                region,
+                vec![],
+                vec![],
+                Type::EmptyTagUnion,
+                AliasKind::Structural,
+            );
+
+            symbol
+        };
+
+        let result = add_local_alias("Result", result_region);
+        let dict = add_local_alias("Dict", dict_region);
+
+        assert_eq!(
+            scope.shadowed_builtin_aliases(),
+            &[
+                ShadowedBuiltinAlias {
+                    name: Ident::from("Result"),
+                    builtin: Symbol::RESULT_RESULT,
+                    region: result_region,
+                },
+                ShadowedBuiltinAlias {
+                    name: Ident::from("Dict"),
+                    builtin: Symbol::DICT_DICT,
+                    region: dict_region,
+                },
+            ]
+        );
+
+        // from here on, the local aliases are used
+        for (name, local, region) in [
+            ("Result", result, result_region),
+            ("Dict", dict, dict_region),
+        ] {
+            let lookup = scope.lookup(&Ident::from(name), Region::zero()).unwrap();
+
+            assert_eq!(lookup, local);
+            assert_eq!(scope.lookup_alias(lookup).unwrap().region, region);
+        }
+    }
+
+    #[test]
+    fn alias_not_shadowing_builtin_alias() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = Region::from_pos(Position { offset: 10 });
+        let symbol = scope.introduce(Ident::from("Meters"), region).unwrap();
+
+        scope.add_alias(
+            symbol,
+            region,
+            vec![],
+            vec![],
+            Type::EmptyTagUnion,
+            AliasKind::Structural,
+        );
+
+        assert!(scope.shadowed_builtin_aliases().is_empty());
+    }
+
+    #[test]
+    fn lookup_reports_origin() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let local = Ident::from("total");
+        let imported = Ident::from("product");
+
+        let local_symbol = scope.introduce(local.clone(), Region::zero()).unwrap();
+        scope
+            .import(imported.clone(), Symbol::LIST_PRODUCT, Region::zero())
+            .unwrap();
+
+        assert_eq!(
+            scope.lookup_with_origin(&local, Region::zero()).unwrap(),
+            (local_symbol, SymbolOrigin::Local)
+        );
+        assert_eq!(
+            scope.lookup_with_origin(&imported, Region::zero()).unwrap(),
+            (Symbol::LIST_PRODUCT, SymbolOrigin::Imported(ModuleId::LIST))
+        );
+    }
+
+    #[test]
+    fn lookup_recording() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = |start, end| Region::new(Position::new(start), Position::new(end));
+
+        let total = scope.introduce("total".into(), region(0, 5)).unwrap();
+        scope
+            .import("product".into(), Symbol::LIST_PRODUCT, Region::zero())
+            .unwrap();
+
+        let mut refs = Vec::new();
+
+        for (name, use_region) in [
+            ("total", region(10, 15)),
+            ("product", region(20, 27)),
+            ("missing", region(30, 37)),
+            ("total", region(40, 45)),
+        ] {
+            let _ = scope.lookup_recording(&name.into(), use_region, &mut refs);
+        }
+
+        assert_eq!(
+            refs,
+            vec![
+                (total, region(10, 15)),
+                (Symbol::LIST_PRODUCT, region(20, 27)),
+                (total, region(40, 45)),
+            ]
+        );
+    }
+
+    #[test]
+    fn idents_matching_prefix() {
+        let mut module_ids = ModuleIds::default();
+        let parser = module_ids.get_or_insert(&"Parser".into());
+        let lines = Symbol::new(parser, IdentIds::default().add_str("lines"));
+
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = |offset| Region::from_pos(Position { offset });
+
+        let listing = scope.introduce("listing".into(), region(10)).unwrap();
+        let limit = scope.introduce("Limit".into(), region(20)).unwrap();
+        scope.add_alias(
+            limit,
+            region(20),
+            vec![],
+            vec![],
+            Type::EmptyTagUnion,
+            AliasKind::Structural,
+        );
+
+        // an ability member, and a specialization of it
+        let hoist = scope.introduce("Hoist".into(), region(30)).unwrap();
+        let lift = scope.introduce("lift".into(), region(40)).unwrap();
+        scope.abilities_store.register_ability(
+            hoist,
+            [(
+                lift,
+                AbilityMemberData {
+                    parent_ability: hoist,
+                    region: region(40),
+                    typ: PendingMemberType::Imported,
+                },
+            )],
+        );
+        let (_, shadowed) = scope
+            .introduce_or_shadow_ability_member(&VecMap::default(), "lift".into(), region(50))
+            .unwrap();
+        assert_eq!(shadowed, Some(lift));
+
+        scope.import("lines".into(), lines, region(60)).unwrap();
+        scope
+            .import("lit".into(), Symbol::STR_CONCAT, region(70))
+            .unwrap();
+        scope
+            .import("len".into(), Symbol::LIST_LEN, region(80))
+            .unwrap();
+
+        let completion = |ident: &str, symbol, kind, offset| Completion {
+            ident: ident.into(),
+            symbol,
+            kind,
+            region: region(offset),
+        };
+
+        assert_eq!(
+            scope.idents_matching("li"),
+            vec![
+                completion("lift", lift, CompletionKind::AbilityMember, 40),
+                completion("listing", listing, CompletionKind::Value, 10),
+                completion("lines", lines, CompletionKind::Import, 60),
+                completion("lit", Symbol::STR_CONCAT, CompletionKind::Builtin, 70),
+            ]
+        );
+
+        // no exact prefix match, so case is ignored
+        assert_eq!(
+            scope.idents_matching("lim"),
+            vec![completion("Limit", limit, CompletionKind::Alias, 20)]
+        );
+
+        assert!(scope.idents_matching("xyz").is_empty());
+    }
+
+    #[test]
+    fn idents_matching_empty_prefix() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        scope
+            .import("len".into(), Symbol::LIST_LEN, Region::zero())
+            .unwrap();
+        scope.introduce("x".into(), Region::zero()).unwrap();
+
+        let names: Vec<_> = scope
+            .idents_matching("")
+            .into_iter()
+            .map(|completion| completion.ident)
+            .collect();
+
+        assert_eq!(names, [Ident::from("x"), Ident::from("len")]);
+
+        for i in 0..MAX_COMPLETIONS {
+            scope
+                .introduce(format!("x{}", i).into(), Region::zero())
+                .unwrap();
+        }
+
+        assert_eq!(scope.idents_matching("").len(), MAX_COMPLETIONS);
+    }
+
+    #[test]
+    fn scope_builder() {
+        let _register_module_debug_names = ModuleIds::default();
+
+        let region = Region::from_pos(Position { offset: 10 });
+
+        let scope = ScopeBuilder::new(ModuleId::ATTR)
+            .import("product".into(), Symbol::LIST_PRODUCT, region)
+            .alias(
+                "Meters".into(),
+                region,
+                vec![],
+                Type::EmptyTagUnion,
+                AliasKind::Opaque,
+            )
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            scope.lookup(&"product".into(), Region::zero()),
+            Ok(Symbol::LIST_PRODUCT)
+        );
+
+        let meters = scope.lookup(&"Meters".into(), Region::zero()).unwrap();
+        assert_eq!(scope.lookup_alias(meters).unwrap().kind, AliasKind::Opaque);
+    }
+
+    #[test]
+    fn scope_builder_reports_all_conflicts() {
+        let _register_module_debug_names = ModuleIds::default();
+
+        let region1 = Region::from_pos(Position { offset: 10 });
+        let region2 = Region::from_pos(Position { offset: 20 });
+        let region3 = Region::from_pos(Position { offset: 30 });
+
+        let errors = ScopeBuilder::new(ModuleId::ATTR)
+            .import("product".into(), Symbol::LIST_PRODUCT, region1)
+            .import("product".into(), Symbol::LIST_SUM, region2)
+            .alias(
+                "product".into(),
+                region3,
+                vec![],
+                Type::EmptyTagUnion,
+                AliasKind::Structural,
+            )
+            .build()
+            .unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                ScopeBuildError::DuplicateImport {
+                    original: Loc::at(region1, Symbol::LIST_PRODUCT),
+                    shadow: Loc::at(region2, "product".into()),
+                },
+                ScopeBuildError::AliasShadows {
+                    original: Loc::at(region1, Symbol::LIST_PRODUCT),
+                    shadow: Loc::at(region3, "product".into()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn lookup_or_recover_shares_placeholder() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        scope.introduce("known".into(), Region::zero()).unwrap();
+
+        let missing = Ident::from("missing");
+        let results: Vec<_> = (0..3)
+            .map(|offset| {
+                let region = Region::from_pos(Position { offset });
+                scope.lookup_or_recover(&missing, region)
+            })
+            .collect();
+
+        let placeholder = results[0].0;
+        assert!(results.iter().all(|(symbol, _)| *symbol == placeholder));
+
+        let errors: Vec<_> = results.into_iter().filter_map(|(_, error)| error).collect();
+        assert!(matches!(
+            errors.as_slice(),
+            [RuntimeError::LookupNotInScope(loc_ident, _)] if loc_ident.value == missing
+        ));
+
+        // the placeholder is not offered as a suggestion
+        assert_eq!(scope.num_idents(), 1);
+        assert!(scope.idents_matching("mis").is_empty());
+        match scope.lookup(&"missin".into(), Region::zero()) {
+            Err(RuntimeError::LookupNotInScope(_, suggestions)) => {
+                assert!(suggestions.iter().all(|s| &**s != "missing"))
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn rollback_to_with_var_store() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+        let mut var_store = VarStore::default();
+
+        let total = scope.introduce("total".into(), Region::zero()).unwrap();
+        let next_var = var_store.peek();
+        let snapshot = scope.snapshot_with_var_store(&var_store);
+
+        let temp = scope.introduce("temp".into(), Region::zero()).unwrap();
+        let (alias, _) = list_alias(&mut var_store);
+        scope.insert_alias(Symbol::LIST_LIST, alias);
+        scope
+            .import("sum".into(), Symbol::LIST_SUM, Region::zero())
+            .unwrap();
+
+        scope.rollback_to_with_var_store(snapshot, &mut var_store);
+
+        assert_eq!(var_store.fresh().index(), next_var);
+        assert!(scope.lookup_alias(Symbol::LIST_LIST).is_none());
+        assert_eq!(
+            scope.iter_bindings().map(|(_, s, _)| s).collect::<Vec<_>>(),
+            vec![total]
+        );
+        assert_eq!(scope.lookup(&"temp".into(), Region::zero()).ok(), None);
+        assert_eq!(
+            scope.locals.ident_ids.get_name(temp.ident_id()),
+            Some("temp")
+        );
+    }
+
+    #[test]
+    fn rollback_to_without_var_store() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+        let mut var_store = VarStore::default();
+
+        let snapshot = scope.snapshot();
+
+        let (alias, var) = list_alias(&mut var_store);
+        scope.insert_alias(Symbol::LIST_LIST, alias);
+
+        scope.rollback_to(snapshot);
+
+        assert!(scope.lookup_alias(Symbol::LIST_LIST).is_none());
+        assert_ne!(var_store.fresh(), var);
+    }
+
+    #[test]
+    fn rollback_to_restores_overwritten_state() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = |start, end| Region::new(Position::new(start), Position::new(end));
+
+        let age = scope.introduce("Age".into(), region(0, 10)).unwrap();
+        let years = scope.introduce("Years".into(), region(20, 30)).unwrap();
+        let total = scope.introduce("total".into(), region(40, 50)).unwrap();
+        for symbol in [age, years] {
+            scope.add_alias(
+                symbol,
+                region(0, 10),
+                vec![],
+                vec![],
+                Type::EmptyRec,
+                AliasKind::Structural,
+            );
+        }
+
+        let snapshot = scope.snapshot();
+
+        // overwrite `Years` in place, and change state that has no length to roll back to
+        scope.copy_alias(age, years, region(60, 70)).unwrap();
+        scope.mark_deprecated(total, "use count".into());
+        scope.add_module_alias("L".into(), ModuleId::LIST);
+        assert_eq!(scope.lookup(&"total".into(), region(80, 85)), Ok(total));
+        assert!(scope.unused_idents().iter().all(|(_, s, _)| *s != total));
+
+        scope.rollback_to(snapshot);
+
+        assert_eq!(scope.lookup_alias(years).unwrap().region, region(0, 10));
+        assert_eq!(scope.deprecation(total), None);
+        assert_eq!(scope.lookup_module_alias(&"L".into()), None);
+        assert!(scope.unused_idents().iter().any(|(_, s, _)| *s == total));
+    }
+
+    #[test]
+    fn deltas_compose_to_final_scope() {
+        let _register_module_debug_names = ModuleIds::default();
+
+        // the long-lived scope, and the scope one input is canonicalized in
+        let mut persistent = Scope::new_empty(ModuleId::ATTR);
+
+        let region = |offset| Region::from_pos(Position { offset });
+
+        // first input: `total = ...` and `Age : ...`
+        let mut working = persistent.clone();
+        let snapshot = working.snapshot();
+
+        let total = working.introduce("total".into(), region(10)).unwrap();
+        let age = working.introduce("Age".into(), region(20)).unwrap();
+        working.add_alias(
+            age,
+            region(20),
+            vec![],
+            vec![],
+            Type::EmptyTagUnion,
+            AliasKind::Structural,
+        );
+        working.inner_scope(|inner| {
+            inner.introduce("temp".into(), region(30)).unwrap();
+        });
+
+        let delta = working.changes_since(&snapshot);
+
+        assert_eq!(
+            delta.introduced,
+            vec![
+                ("total".into(), total, region(10)),
+                ("Age".into(), age, region(20)),
+            ]
+        );
+        assert_eq!(
+            delta.aliases.iter().map(|(s, _)| *s).collect::<Vec<_>>(),
+            vec![age]
+        );
+        assert!(delta.overwritten.is_empty());
+
+        persistent.apply_delta(&delta);
+
+        // second input: `count = ...`, and an import that overwrites `total`
+        let mut working = persistent.clone();
+        let snapshot = working.snapshot();
+
+        let count = working.introduce("count".into(), region(40)).unwrap();
+        working
+            .import("total".into(), Symbol::LIST_SUM, region(50))
+            .unwrap();
+
+        let delta = working.changes_since(&snapshot);
+
+        assert_eq!(delta.introduced, vec![("count".into(), count, region(40))]);
+        assert_eq!(
+            delta.imports,
+            vec![("total".into(), Symbol::LIST_SUM, region(50))]
+        );
+        assert_eq!(delta.overwritten, vec![(total, Symbol::LIST_SUM)]);
+
+        persistent.apply_delta(&delta);
+
+        assert_eq!(
+            persistent.iter_bindings().collect::<Vec<_>>(),
+            working.iter_bindings().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            persistent.aliases.keys().collect::<Vec<_>>(),
+            working.aliases.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            persistent.locals.ident_ids.len(),
+            working.locals.ident_ids.len()
+        );
+
+        for name in ["total", "Age", "count", "temp"] {
+            assert_eq!(
+                persistent.lookup(&name.into(), Region::zero()),
+                working.lookup(&name.into(), Region::zero())
+            );
+        }
+    }
+
+    #[test]
+    fn delta_of_overwritten_alias() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut persistent = Scope::new_empty(ModuleId::ATTR);
+
+        let region = |offset| Region::from_pos(Position { offset });
+
+        let age = persistent.introduce("Age".into(), region(0)).unwrap();
+        let years = persistent.introduce("Years".into(), region(10)).unwrap();
+        for (symbol, offset) in [(age, 0), (years, 10)] {
+            persistent.add_alias(
+                symbol,
+                region(offset),
+                vec![],
+                vec![],
+                Type::EmptyRec,
+                AliasKind::Structural,
+            );
+        }
+
+        let mut working = persistent.clone();
+        let snapshot = working.snapshot();
+
+        // `Years` is overwritten in place, so there are no more aliases than before
+        working.copy_alias(age, years, region(20)).unwrap();
+
+        let delta = working.changes_since(&snapshot);
+        working.commit(snapshot);
+
+        assert_eq!(
+            delta.aliases.iter().map(|(s, _)| *s).collect::<Vec<_>>(),
+            vec![years]
+        );
+
+        persistent.apply_delta(&delta);
+
+        assert_eq!(persistent.lookup_alias(years).unwrap().region, region(20));
+        assert_eq!(persistent.lookup_alias(age).unwrap().region, region(0));
+    }
+
+    #[test]
+    fn delta_of_generated_idents() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut persistent = Scope::new_empty(ModuleId::ATTR);
+
+        let mut working = persistent.clone();
+        let snapshot = working.snapshot();
+
+        let generated = working.gen_unique_symbol();
+        let total = working.introduce("total".into(), Region::zero()).unwrap();
+
+        let delta = working.changes_since(&snapshot);
+        working.commit(snapshot);
+
+        persistent.apply_delta(&delta);
+
+        // the generated name is generated again, rather than interned like a real name
+        let name = working.locals.ident_ids.get_name(generated.ident_id());
+        assert_eq!(
+            persistent.locals.ident_ids.get_name(generated.ident_id()),
+            name
+        );
+        assert_eq!(persistent.locals.ident_ids.get_id(name.unwrap()), None);
+        assert_eq!(
+            persistent.lookup(&"total".into(), Region::zero()),
+            Ok(total)
+        );
+        assert_eq!(persistent.gen_unique_symbol(), working.gen_unique_symbol());
+    }
+
+    #[test]
+    fn introduce_reports_exposed() {
+        let _register_module_debug_names = ModuleIds::default();
+
+        let mut exposed = IdentIds::default();
+        let main = exposed.add_str("main");
+
+        let mut scope = Scope::new(ModuleId::ATTR, exposed, PendingAbilitiesStore::default());
+
+        let (_, was_exposed) = scope
+            .introduce_with_exposed("helper".into(), Region::zero())
+            .unwrap();
+        assert_eq!(was_exposed, WasExposed(false));
+
+        let (symbol, was_exposed) = scope
+            .introduce_with_exposed("main".into(), Region::zero())
+            .unwrap();
+        assert_eq!(symbol, Symbol::new(ModuleId::ATTR, main));
+        assert_eq!(was_exposed, WasExposed(true));
+    }
+
+    fn exposed_scope(names: &[&str]) -> (Scope, IdentIds) {
+        let exposed = exposing(names);
+        let scope = Scope::new(
+            ModuleId::ATTR,
+            exposed.clone(),
+            PendingAbilitiesStore::default(),
+        );
+
+        (scope, exposed)
+    }
+
+    #[test]
+    fn verify_exposed_all_defined() {
+        let _register_module_debug_names = ModuleIds::default();
+        let (mut scope, exposed) = exposed_scope(&["main", "helper"]);
+
+        scope.introduce("helper".into(), Region::zero()).unwrap();
+        scope.introduce("main".into(), Region::zero()).unwrap();
+
+        assert!(scope.verify_exposed(&exposed).is_empty());
+    }
+
+    #[test]
+    fn verify_exposed_partially_defined() {
+        let _register_module_debug_names = ModuleIds::default();
+        let (mut scope, exposed) = exposed_scope(&["main", "helper", "other"]);
+
+        scope.introduce("main".into(), Region::zero()).unwrap();
+
+        // a binding with a name that is not exposed does not count
+        scope.introduce("helpers".into(), Region::zero()).unwrap();
+
+        assert_eq!(
+            scope.verify_exposed(&exposed),
+            vec![Ident::from("helper"), Ident::from("other")]
+        );
+    }
+
+    #[test]
+    fn verify_exposed_specialization() {
+        let _register_module_debug_names = ModuleIds::default();
+        let (mut scope, exposed) = exposed_scope(&["hash"]);
+
+        // `hash` is a member of an imported ability, which this module specializes
+        let region = Region::zero();
+        scope
+            .import("Hash".into(), Symbol::HASH_HASH_ABILITY, region)
+            .unwrap();
+        scope
+            .import("hash".into(), Symbol::HASH_HASH, region)
+            .unwrap();
+        scope.abilities_store.register_ability(
+            Symbol::HASH_HASH_ABILITY,
+            [(
+                Symbol::HASH_HASH,
+                AbilityMemberData {
+                    parent_ability: Symbol::HASH_HASH_ABILITY,
+                    region,
+                    typ: PendingMemberType::Imported,
+                },
+            )],
+        );
+
+        assert_eq!(scope.verify_exposed(&exposed), vec![Ident::from("hash")]);
+
+        scope
+            .introduce_or_shadow_ability_member(&VecMap::default(), "hash".into(), region)
+            .unwrap();
+
+        assert!(scope.verify_exposed(&exposed).is_empty());
+    }
+
+    #[test]
+    fn normalization_forms_are_the_same_name() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let composed = Ident::from("caf\u{e9}");
+        let decomposed = Ident::from("cafe\u{301}");
+        assert_ne!(composed, decomposed);
+
+        let symbol = scope.introduce(composed.clone(), Region::zero()).unwrap();
+        assert_eq!(scope.lookup(&composed, Region::zero()), Ok(symbol));
+        assert_eq!(scope.lookup(&decomposed, Region::zero()), Ok(symbol));
+
+        // and the other way around
+        let composed = Ident::from("na\u{ef}ve");
+        let decomposed = Ident::from("nai\u{308}ve");
+
+        let symbol = scope.introduce(decomposed.clone(), Region::zero()).unwrap();
+        assert_eq!(scope.lookup(&composed, Region::zero()), Ok(symbol));
+        assert_eq!(scope.lookup(&decomposed, Region::zero()), Ok(symbol));
+
+        // introducing the other form is a shadow
+        assert!(scope.introduce(composed, Region::zero()).is_err());
+
+        scope
+            .import("r\u{e9}sum\u{e9}".into(), Symbol::LIST_SUM, Region::zero())
+            .unwrap();
+        assert_eq!(
+            scope.lookup(&"re\u{301}sume\u{301}".into(), Region::zero()),
+            Ok(Symbol::LIST_SUM)
+        );
+    }
+
+    #[test]
+    fn lookup_of_look_alike_ident() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let ligature = Ident::from("\u{fb01}le");
+        scope.introduce(ligature.clone(), Region::zero()).unwrap();
+        scope.introduce("files".into(), Region::zero()).unwrap();
+
+        assert_eq!(
+            scope.lookup(&"file".into(), Region::zero()),
+            Err(RuntimeError::LookupNotInScopeButLooksAlike {
+                usage: Loc::at(Region::zero(), "file".into()),
+                similar: ligature,
+            })
+        );
+
+        // ASCII names that differ are not similar
+        assert!(matches!(
+            scope.lookup(&"fil".into(), Region::zero()),
+            Err(RuntimeError::LookupNotInScope(..))
+        ));
+    }
+
+    #[test]
+    fn aliases_in_region() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = |start, end| Region::new(Position::new(start), Position::new(end));
+
+        let mut symbols = Vec::new();
+        for (name, alias_region) in [
+            ("Before", region(0, 10)),
+            ("First", region(20, 30)),
+            ("Second", region(30, 40)),
+            ("Overlapping", region(35, 55)),
+            ("After", region(60, 70)),
+        ] {
+            let symbol = scope.introduce(name.into(), alias_region).unwrap();
+            scope.add_alias(
+                symbol,
+                alias_region,
+                vec![],
+                vec![],
+                Type::EmptyTagUnion,
+                AliasKind::Structural,
+            );
+            symbols.push(symbol);
+        }
+
+        let selected = |query| {
+            scope
+                .aliases_in_region(query)
+                .into_iter()
+                .map(|(symbol, _)| symbol)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(selected(region(15, 50)), vec![symbols[1], symbols[2]]);
+
+        // the bounds are inclusive
+        assert_eq!(selected(region(20, 30)), vec![symbols[1]]);
+
+        assert_eq!(selected(region(0, 70)), symbols);
+        assert_eq!(selected(region(11, 19)), vec![]);
+    }
+
+    #[test]
+    fn specializations_are_not_in_scope() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = Region::zero();
+        let ability = scope.introduce("Ability".into(), region).unwrap();
+        let members: Vec<_> = (0..100)
+            .map(|i| scope.introduce(format!("m{}", i).into(), region).unwrap())
+            .collect();
+
+        scope.abilities_store.register_ability(
+            ability,
+            members.iter().map(|member| {
+                let data = AbilityMemberData {
+                    parent_ability: ability,
+                    region,
+                    typ: PendingMemberType::Imported,
+                };
+
+                (*member, data)
+            }),
+        );
+
+        let num_idents = scope.num_idents();
+
+        for (i, member) in members.iter().enumerate() {
+            let (shadow, shadowed) = scope
+                .introduce_or_shadow_ability_member(
+                    &VecMap::default(),
+                    format!("m{}", i).into(),
+                    region,
+                )
+                .unwrap();
+
+            assert_eq!(shadowed, Some(*member));
+            assert_eq!(scope.lookup_ability_member_shadow(*member), Some(shadow));
+        }
+
+        assert_eq!(scope.num_idents(), num_idents);
+        assert_eq!(scope.iter_bindings().count(), num_idents);
+
+        for (i, member) in members.iter().enumerate() {
+            let lookup = scope.lookup(&format!("m{}", i).into(), region);
+
+            assert_eq!(lookup, Ok(*member));
+        }
+    }
+
+    #[test]
+    fn recursive_group_with_duplicate() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = |offset| Region::from_pos(Position { offset });
+
+        let (symbols, conflicts) = scope.introduce_recursive_group(&[
+            ("isEven".into(), region(10)),
+            ("isOdd".into(), region(20)),
+            ("isEven".into(), region(30)),
+        ]);
+
+        assert_eq!(
+            conflicts,
+            vec![GroupConflict::Duplicate {
+                original: Loc::at(region(10), "isEven".into()),
+                duplicate: Loc::at(region(30), "isEven".into()),
+            }]
+        );
+
+        assert_eq!(symbols.len(), 3);
+        assert_ne!(symbols[0], symbols[2]);
+
+        assert_eq!(scope.lookup(&"isEven".into(), region(0)), Ok(symbols[0]));
+        assert_eq!(scope.lookup(&"isOdd".into(), region(0)), Ok(symbols[1]));
+        assert_eq!(scope.num_idents(), 2);
+    }
+
+    #[test]
+    fn recursive_group_colliding_with_import() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = |offset| Region::from_pos(Position { offset });
+
+        scope
+            .import("sum".into(), Symbol::LIST_SUM, region(0))
+            .unwrap();
+
+        let (symbols, conflicts) = scope
+            .introduce_recursive_group(&[("total".into(), region(10)), ("sum".into(), region(20))]);
+
+        assert_eq!(
+            conflicts,
+            vec![GroupConflict::Shadows {
+                original: Loc::at(region(0), Symbol::LIST_SUM),
+                shadow: Loc::at(region(20), "sum".into()),
+            }]
+        );
+
+        assert_eq!(scope.lookup(&"total".into(), region(0)), Ok(symbols[0]));
+        assert_eq!(scope.lookup(&"sum".into(), region(0)), Ok(Symbol::LIST_SUM));
+        assert_ne!(symbols[1], Symbol::LIST_SUM);
+    }
+
+    #[test]
+    fn local_aliases() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let add_alias = |scope: &mut Scope, symbol| {
+            scope.add_alias(
+                symbol,
+                Region::zero(),
+                vec![],
+                vec![],
+                Type::EmptyTagUnion,
+                AliasKind::Structural,
+            )
+        };
+
+        let age = scope.introduce("Age".into(), Region::zero()).unwrap();
+        add_alias(&mut scope, Symbol::RESULT_RESULT);
+        add_alias(&mut scope, age);
+        add_alias(&mut scope, Symbol::DICT_DICT);
+
+        let local: Vec<_> = scope.local_aliases().map(|(symbol, _)| symbol).collect();
+
+        assert_eq!(local, vec![age]);
+        assert_eq!(scope.aliases.len(), 3);
+    }
+
+    #[test]
+    fn take_aliases() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let age = scope.introduce("Age".into(), Region::zero()).unwrap();
+        scope.add_alias(
+            age,
+            Region::zero(),
+            vec![],
+            vec![],
+            Type::EmptyRec,
+            AliasKind::Structural,
+        );
+
+        let aliases = scope.take_aliases();
+
+        assert_eq!(aliases.keys().collect::<Vec<_>>(), vec![&age]);
+        assert_eq!(scope.local_aliases().count(), 0);
+        assert!(scope.lookup_alias(age).is_none());
+    }
+
+    #[test]
+    fn aliases_generation() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let add_alias = |scope: &mut Scope, symbol| {
+            scope.add_alias(
+                symbol,
+                Region::zero(),
+                vec![],
+                vec![],
+                Type::EmptyRec,
+                AliasKind::Opaque,
+            )
+        };
+
+        let age = scope.introduce("Age".into(), Region::zero()).unwrap();
+        let generation = scope.aliases_generation();
+
+        // a cache of the opaque lookup, keyed on the generation
+        let lookup = |scope: &Scope| {
+            let result = scope.lookup_opaque_ref("@Age", Region::zero()).is_ok();
+            (scope.aliases_generation(), result)
+        };
+        let cached = lookup(&scope);
+        assert_eq!(cached, (generation, false));
+
+        add_alias(&mut scope, age);
+        assert_ne!(scope.aliases_generation(), cached.0);
+        let cached = lookup(&scope);
+        assert!(cached.1);
+        assert_eq!(scope.alias_count(), 1);
+
+        // reads, and inner scopes without aliases, keep the cache valid
+        let _ = scope.lookup_alias(age);
+        let _ = scope.aliases().len();
+        scope.inner_scope(|inner| {
+            inner.introduce("temp".into(), Region::zero()).unwrap();
+        });
+        assert_eq!(scope.aliases_generation(), cached.0);
+
+        // an alias that is dropped at the end of an inner scope invalidates it
+        scope.inner_scope(|inner| {
+            let temp = inner.introduce("Temp".into(), Region::zero()).unwrap();
+            add_alias(inner, temp);
+        });
+        assert_ne!(scope.aliases_generation(), cached.0);
+
+        let cached = lookup(&scope);
+        assert_eq!(
+            scope.remove_alias(age).map(|alias| alias.kind),
+            Some(AliasKind::Opaque)
+        );
+        assert_ne!(scope.aliases_generation(), cached.0);
+        assert!(!lookup(&scope).1);
+        assert_eq!(scope.alias_count(), 0);
+    }
+
+    fn imported_alias(symbol: Symbol, kind: AliasKind) -> Alias {
+        create_alias(
+            symbol,
+            Region::zero(),
+            vec![],
+            vec![],
+            Type::EmptyTagUnion,
+            kind,
+        )
+    }
+
+    #[test]
+    fn import_alias() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = Region::from_pos(Position { offset: 10 });
+        let alias = imported_alias(Symbol::DICT_DICT, AliasKind::Structural);
+
+        assert_eq!(
+            scope.import_alias("Dict".into(), Symbol::DICT_DICT, alias, region),
+            Ok(())
+        );
+
+        assert_eq!(
+            scope.lookup(&"Dict".into(), Region::zero()),
+            Ok(Symbol::DICT_DICT)
+        );
+        assert!(scope.lookup_alias(Symbol::DICT_DICT).is_some());
+        assert_eq!(scope.local_aliases().count(), 0);
+
+        // importing the same name twice is still a shadow
+        let alias = imported_alias(Symbol::DICT_DICT, AliasKind::Structural);
+        assert_eq!(
+            scope.import_alias("Dict".into(), Symbol::DICT_DICT, alias, Region::zero()),
+            Err((Symbol::DICT_DICT, region))
+        );
+    }
+
+    #[test]
+    fn import_alias_conflicts_with_local_alias() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = Region::from_pos(Position { offset: 10 });
+        let local = scope.introduce("Set".into(), region).unwrap();
+        scope.add_alias(
+            local,
+            region,
+            vec![],
+            vec![],
+            Type::EmptyTagUnion,
+            AliasKind::Structural,
+        );
+
+        let alias = imported_alias(Symbol::SET_SET, AliasKind::Structural);
+        assert_eq!(
+            scope.import_alias("Set".into(), Symbol::SET_SET, alias, Region::zero()),
+            Err((local, region))
+        );
+
+        assert!(scope.lookup_alias(Symbol::SET_SET).is_none());
+        assert_eq!(scope.lookup(&"Set".into(), Region::zero()), Ok(local));
+    }
+
+    #[test]
+    fn imported_opaque_cannot_be_wrapped() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = Region::from_pos(Position { offset: 10 });
+        let alias = imported_alias(Symbol::DICT_DICT, AliasKind::Opaque);
+
+        scope
+            .import_alias("Dict".into(), Symbol::DICT_DICT, alias, region)
+            .unwrap();
+
+        assert_eq!(
+            scope
+                .lookup_alias(Symbol::DICT_DICT)
+                .map(|alias| alias.kind),
+            Some(AliasKind::Opaque)
+        );
+
+        let lookup_region = Region::from_pos(Position { offset: 20 });
+        assert_eq!(
+            scope
+                .lookup_opaque_ref("@Dict", lookup_region)
+                .map(|(s, _)| s),
+            Err(RuntimeError::OpaqueOutsideScope {
+                opaque: "Dict".into(),
+                referenced_region: lookup_region,
+                imported_region: region,
+            })
+        );
+    }
+
+    #[test]
+    fn introduce_with_symbol_round_trip() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut original = Scope::new_empty(ModuleId::ATTR);
+
+        original.introduce("first".into(), Region::zero()).unwrap();
+        original.gen_unique_symbol();
+        original.introduce("second".into(), Region::zero()).unwrap();
+        original.scopeless_symbol(&"unused".into(), Region::zero());
+        original
+            .introduce("third".into(), Region::from_pos(Position { offset: 30 }))
+            .unwrap();
+
+        // what would be stored in the cache
+        let cached: Vec<(String, Symbol, Region)> = original
+            .iter_bindings()
+            .map(|(ident, symbol, region)| (ident.to_string(), symbol, region))
+            .collect();
+
+        let mut rebuilt = Scope::new_empty(ModuleId::ATTR);
+
+        // the order in which they are introduced does not matter
+        for (name, symbol, region) in cached.iter().rev() {
+            rebuilt
+                .introduce_with_symbol(name.as_str().into(), *symbol, *region)
+                .unwrap();
+        }
+
+        for (name, symbol, _) in cached.iter() {
+            let lookup = rebuilt.lookup(&name.as_str().into(), Region::zero());
+
+            assert_eq!(lookup, Ok(*symbol));
+            assert_eq!(
+                lookup,
+                original.lookup(&name.as_str().into(), Region::zero())
+            );
+        }
+
+        assert_eq!(
+            rebuilt.iter_bindings().collect::<Vec<_>>(),
+            original.iter_bindings().collect::<Vec<_>>()
+        );
+
+        // new identifiers do not reuse the symbols of the cached ones
+        let fresh = rebuilt.introduce("fourth".into(), Region::zero()).unwrap();
+        assert!(cached.iter().all(|(_, symbol, _)| *symbol != fresh));
+    }
+
+    #[test]
+    fn introduce_with_symbol_shadow() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let symbol = scope.introduce("total".into(), Region::zero()).unwrap();
+        let region = Region::from_pos(Position { offset: 10 });
+        let other = scope.scopeless_symbol(&"total".into(), region);
+
+        assert_eq!(
+            scope.introduce_with_symbol("total".into(), other, region),
+            Err(ShadowError {
+                original: Loc::at(Region::zero(), symbol),
+                shadow: Loc::at(region, "total".into()),
+            })
+        );
+    }
+
+    #[test]
+    fn all_symbols_for_name() {
+        let _register_module_debug_names = ModuleIds::default();
+        let (mut scope, _) = exposed_scope(&["total"]);
+
+        let region = |offset| Region::from_pos(Position { offset });
+
+        assert!(scope.all_symbols_for_name(&"total".into()).is_empty());
+
+        let first = scope.introduce("total".into(), region(10)).unwrap();
+        let (_, _, second) = scope.introduce("total".into(), region(20)).unwrap_err();
+        let third = scope.inner_scope(|inner| {
+            inner.lookup_or_recover(&"totl".into(), region(25));
+            inner.introduce("total".into(), region(30)).unwrap_err().2
+        });
+
+        assert_eq!(
+            scope.all_symbols_for_name(&"total".into()),
+            vec![
+                (first, region(10)),
+                (second, region(20)),
+                (third, region(30))
+            ]
+        );
+        assert!(scope.all_symbols_for_name(&"totl".into()).is_empty());
+    }
+
+    #[test]
+    fn home_symbols() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        assert_eq!(scope.home(), ModuleId::ATTR);
+
+        scope
+            .import("sum".into(), Symbol::LIST_SUM, Region::zero())
+            .unwrap();
+        let total = scope.introduce("total".into(), Region::zero()).unwrap();
+        scope
+            .import("Dict".into(), Symbol::DICT_DICT, Region::zero())
+            .unwrap();
+        let count = scope.introduce("count".into(), Region::zero()).unwrap();
+
+        assert!(scope.is_home_symbol(total));
+        assert!(!scope.is_home_symbol(Symbol::LIST_SUM));
+
+        assert_eq!(scope.home_symbols().collect::<Vec<_>>(), vec![total, count]);
+
+        // symbols that left the scope are not included
+        scope.inner_scope(|inner| {
+            let temp = inner.introduce("temp".into(), Region::zero()).unwrap();
+            assert_eq!(
+                inner.home_symbols().collect::<Vec<_>>(),
+                vec![total, count, temp]
+            );
+        });
+
+        assert_eq!(scope.home_symbols().collect::<Vec<_>>(), vec![total, count]);
+    }
+
+    #[test]
+    fn user_symbols_in_order() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = |offset| Region::from_pos(Position { offset });
+
+        scope
+            .import("sum".into(), Symbol::LIST_SUM, region(0))
+            .unwrap();
+        let ability = scope.introduce("Hoist".into(), region(10)).unwrap();
+        let member = scope.introduce("lift".into(), region(20)).unwrap();
+        scope.abilities_store.register_ability(
+            ability,
+            [(
+                member,
+                AbilityMemberData {
+                    parent_ability: ability,
+                    region: region(20),
+                    typ: PendingMemberType::Imported,
+                },
+            )],
+        );
+
+        // the specialization's symbol is not in scope
+        let (specialization, _) = scope
+            .introduce_or_shadow_ability_member(&VecMap::default(), "lift".into(), region(30))
+            .unwrap();
+        let total = scope.introduce("total".into(), region(40)).unwrap();
+
+        let symbols: Vec<_> = scope.user_symbols_in_order().collect();
+        assert_eq!(
+            symbols,
+            vec![
+                (ability, region(10)),
+                (member, region(20)),
+                (total, region(40))
+            ]
+        );
+        assert!(!symbols.iter().any(|(symbol, _)| *symbol == specialization));
+    }
+
+    #[test]
+    fn ident_ids_names() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let x = scope.introduce("x".into(), Region::zero()).unwrap();
+
+        // leaving the inner scope and introducing `y` again creates a duplicate IdentId
+        let inner_y =
+            scope.inner_scope(|inner| inner.introduce("y".into(), Region::zero()).unwrap());
+        let y = scope.introduce("y".into(), Region::zero()).unwrap();
+        assert_ne!(inner_y, y);
+
+        let generated = scope.gen_unique_symbol();
+
+        let ident_ids = &scope.locals.ident_ids;
+
+        assert_eq!(ident_ids.get_name(x.ident_id()), Some("x"));
+        assert_eq!(ident_ids.get_name(inner_y.ident_id()), Some("y"));
+        assert_eq!(ident_ids.get_name(y.ident_id()), Some("y"));
+        assert_eq!(ident_ids.get_name(generated.ident_id()), Some("3"));
+
+        assert_eq!(
+            ident_ids.ident_strs().collect::<Vec<_>>(),
+            vec![
+                (x.ident_id(), "x"),
+                (inner_y.ident_id(), "y"),
+                (y.ident_id(), "y"),
+                (generated.ident_id(), "3"),
+            ]
+        );
+
+        assert_eq!(ident_ids.len(), 4);
+        assert!(ident_ids.capacity() >= ident_ids.len());
+    }
+
+    #[test]
+    fn lookup_or_introduce() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = |offset| Region::from_pos(Position { offset });
+
+        // absent: a fresh symbol is introduced
+        let x = scope.lookup_or_introduce(&"x".into(), region(10));
+        assert_eq!(scope.lookup(&"x".into(), region(20)), Ok(x));
+        assert_eq!(
+            scope.locals.has_in_scope(&"x".into()),
+            Some((x, region(10)))
+        );
+
+        // present: the existing symbol, without creating a shadow
+        let ident_count = scope.locals.ident_ids.len();
+        assert_eq!(scope.lookup_or_introduce(&"x".into(), region(30)), x);
+        assert_eq!(scope.locals.ident_ids.len(), ident_count);
+        assert_eq!(
+            scope.locals.has_in_scope(&"x".into()),
+            Some((x, region(10)))
+        );
+
+        scope
+            .import("sum".into(), Symbol::LIST_SUM, region(40))
+            .unwrap();
+        assert_eq!(
+            scope.lookup_or_introduce(&"sum".into(), region(50)),
+            Symbol::LIST_SUM
+        );
+
+        // out of scope again: a new symbol
+        let inner = scope.inner_scope(|inner| inner.lookup_or_introduce(&"y".into(), region(60)));
+        let outer = scope.lookup_or_introduce(&"y".into(), region(70));
+        assert_ne!(inner, outer);
+    }
+
+    #[test]
+    fn module_aliases() {
+        use crate::env::Env;
+        use bumpalo::Bump;
+
+        let module_ids = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let arena = Bump::new();
+        let dep_idents = IdentIds::exposed_builtins(0);
+        let mut env = Env::new(&arena, ModuleId::ATTR, &dep_idents, &module_ids);
+
+        let alias = ModuleName::from("Collection");
+        let region = Region::zero();
+
+        assert_eq!(scope.lookup_module_alias(&alias), None);
+        assert_eq!(scope.add_module_alias(alias.clone(), ModuleId::LIST), None);
+        assert_eq!(scope.lookup_module_alias(&alias), Some(ModuleId::LIST));
+        assert_eq!(
+            env.qualified_lookup(&scope, "Collection", "sum", region),
+            Ok(Symbol::LIST_SUM)
+        );
+
+        // overwriting reports the module the alias referred to before
+        assert_eq!(
+            scope.add_module_alias(alias.clone(), ModuleId::DICT),
+            Some(ModuleId::LIST)
+        );
+        assert_eq!(scope.lookup_module_alias(&alias), Some(ModuleId::DICT));
+        assert_eq!(
+            env.qualified_lookup(&scope, "Collection", "len", region),
+            Ok(Symbol::DICT_LEN)
+        );
+
+        assert_eq!(scope.remove_module_alias(&alias), Some(ModuleId::DICT));
+        assert_eq!(scope.remove_module_alias(&alias), None);
+        assert_eq!(scope.lookup_module_alias(&alias), None);
+        assert!(matches!(
+            env.qualified_lookup(&scope, "Collection", "len", region),
+            Err(RuntimeError::ModuleNotImported { .. })
+        ));
+    }
+
+    #[test]
+    fn referenced_modules() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        scope
+            .import("sum".into(), Symbol::LIST_SUM, Region::zero())
+            .unwrap();
+        scope
+            .import("map".into(), Symbol::LIST_MAP, Region::zero())
+            .unwrap();
+        scope.introduce("total".into(), Region::zero()).unwrap();
+
+        let alias = imported_alias(Symbol::DICT_DICT, AliasKind::Structural);
+        scope
+            .import_alias("Dict".into(), Symbol::DICT_DICT, alias, Region::zero())
+            .unwrap();
+
+        let age = scope.introduce("Age".into(), Region::zero()).unwrap();
+        scope.add_alias(
+            age,
+            Region::zero(),
+            vec![],
+            vec![],
+            Type::EmptyRec,
+            AliasKind::Structural,
+        );
+
+        assert_eq!(
+            scope.referenced_modules().collect::<Vec<_>>(),
+            vec![ModuleId::LIST, ModuleId::DICT]
+        );
+    }
+
+    #[test]
+    fn unused_imports() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = |offset| Region::from_pos(Position { offset });
+
+        scope
+            .import("sum".into(), Symbol::LIST_SUM, region(0))
+            .unwrap();
+        scope
+            .import("map".into(), Symbol::LIST_MAP, region(10))
+            .unwrap();
+        scope
+            .import("walk".into(), Symbol::LIST_WALK, region(20))
+            .unwrap();
+
+        assert_eq!(
+            scope.lookup(&"sum".into(), region(30)),
+            Ok(Symbol::LIST_SUM)
+        );
+        assert_eq!(
+            scope.unused_imports(),
+            vec![
+                ("map".into(), Symbol::LIST_MAP, region(10)),
+                ("walk".into(), Symbol::LIST_WALK, region(20)),
+            ]
+        );
+
+        // after a local shadows `walk`, its lookups are not uses of the import
+        assert!(scope.introduce("walk".into(), region(40)).is_err());
+        let _ = scope.lookup(&"walk".into(), region(50));
+
+        assert_eq!(
+            scope.unused_imports(),
+            vec![
+                ("map".into(), Symbol::LIST_MAP, region(10)),
+                ("walk".into(), Symbol::LIST_WALK, region(20)),
+            ]
+        );
+
+        // the default imports are not reported
+        let scope = Scope::new(
+            ModuleId::ATTR,
+            IdentIds::default(),
+            PendingAbilitiesStore::default(),
+        );
+        assert_eq!(scope.unused_imports(), vec![]);
+    }
+
+    #[test]
+    fn unused_idents() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = |offset| Region::from_pos(Position { offset });
+
+        scope.introduce("_ignored".into(), region(0)).unwrap();
+        let used = scope.introduce("used".into(), region(10)).unwrap();
+
+        assert_eq!(
+            scope.unused_idents(),
+            vec![("used".into(), used, region(10))]
+        );
+
+        assert_eq!(scope.lookup(&"used".into(), region(20)), Ok(used));
+        assert_eq!(scope.unused_idents(), vec![]);
+    }
+
+    #[test]
+    fn deprecated_local_def() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let old = scope.introduce("oldSum".into(), Region::zero()).unwrap();
+        let new = scope.introduce("sum".into(), Region::zero()).unwrap();
+
+        scope.mark_deprecated(old, "use `sum` instead".into());
+
+        assert_eq!(
+            scope.lookup_full(&"oldSum".into(), Region::zero()),
+            Ok((old, Some(&DeprecationNote("use `sum` instead".into()))))
+        );
+        assert_eq!(
+            scope.lookup_full(&"sum".into(), Region::zero()),
+            Ok((new, None))
+        );
+
+        // plain lookups are unaffected
+        assert_eq!(scope.lookup(&"oldSum".into(), Region::zero()), Ok(old));
+    }
+
+    #[test]
+    fn deprecated_import() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        scope
+            .import("sum".into(), Symbol::LIST_SUM, Region::zero())
+            .unwrap();
+        scope.mark_deprecated(Symbol::LIST_SUM, "use `List.walk` instead".into());
+
+        let (symbol, note) = scope.lookup_str_full("sum", Region::zero()).unwrap();
+
+        assert_eq!(symbol, Symbol::LIST_SUM);
+        assert_eq!(
+            note,
+            Some(&DeprecationNote("use `List.walk` instead".into()))
+        );
+    }
+
+    #[test]
+    fn deprecated_symbols_are_still_suggested() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let old = scope.introduce("oldSum".into(), Region::zero()).unwrap();
+        let before = scope.idents_matching("old");
+
+        scope.mark_deprecated(old, "use `sum` instead".into());
+
+        assert_eq!(scope.idents_matching("old"), before);
+
+        match scope.lookup(&"oldSun".into(), Region::zero()) {
+            Err(RuntimeError::LookupNotInScope(_, suggestions)) => {
+                assert!(suggestions.contains("oldSum"));
+            }
+            other => panic!("expected a lookup error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn shadowing_a_builtin() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = Region::from_pos(Position { offset: 10 });
+        scope
+            .import("map".into(), Symbol::LIST_MAP, region)
+            .unwrap();
+
+        let (original, _, _) = scope.introduce("map".into(), Region::zero()).unwrap_err();
+
+        assert_eq!(original, Loc::at(region, Symbol::LIST_MAP));
+        assert_eq!(
+            scope.shadow_kind(original.value),
+            ShadowKind::Builtin(Symbol::LIST_MAP)
+        );
+    }
+
+    #[test]
+    fn shadowing_a_user_binding() {
+        let mut module_ids = ModuleIds::default();
+        let parser = module_ids.get_or_insert(&"Parser".into());
+        let lines = Symbol::new(parser, IdentIds::default().add_str("lines"));
+
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let total = scope.introduce("total".into(), Region::zero()).unwrap();
+        let (original, _, _) = scope.introduce("total".into(), Region::zero()).unwrap_err();
+        assert_eq!(original.value, total);
+        assert_eq!(scope.shadow_kind(original.value), ShadowKind::Variable);
+
+        // an import of a value from a module that is not a builtin
+        scope.import("lines".into(), lines, Region::zero()).unwrap();
+
+        let (original, _, _) = scope.introduce("lines".into(), Region::zero()).unwrap_err();
+        assert_eq!(original.value, lines);
+        assert_eq!(scope.shadow_kind(original.value), ShadowKind::Variable);
+    }
+
+    #[test]
+    fn shadowing_own_builtin() {
+        let _register_module_debug_names = ModuleIds::default();
+
+        // builtin modules are not told they shadow themselves
+        let mut scope = Scope::new_empty(ModuleId::LIST);
+        let map = scope.introduce("map".into(), Region::zero()).unwrap();
+
+        assert_eq!(scope.shadow_kind(map), ShadowKind::Variable);
+    }
+
+    #[test]
+    fn pre_introduce_top_level_forward_references() {
+        let _register_module_debug_names = ModuleIds::default();
+        let (mut scope, exposed) = exposed_scope(&["main"]);
 
-    fn introduce_into_scope(&mut self, ident_name: &str, region: Region) -> IdentId {
-        let id = self.ident_ids.add_str(ident_name);
+        let region = |offset| Region::from_pos(Position { offset });
 
-        debug_assert_eq!(id.index(), self.in_scope.len());
-        debug_assert_eq!(id.index(), self.regions.len());
+        let (symbols, conflicts) = scope
+            .pre_introduce_top_level(&[("main".into(), region(0)), ("helper".into(), region(10))]);
 
-        self.in_scope.push(true);
-        self.regions.push(region);
+        assert!(conflicts.is_empty());
+        assert_eq!(symbols[0].ident_id(), exposed.get_id("main").unwrap());
 
-        id
-    }
+        // `main` refers to `helper`, which is defined after it
+        assert_eq!(scope.lookup(&"helper".into(), region(5)), Ok(symbols[1]));
 
-    fn introduce_into_scope_duplicate(&mut self, existing: IdentId, region: Region) -> IdentId {
-        let id = self.ident_ids.duplicate_ident(existing);
+        // the definitions get the pre-introduced symbols
+        assert_eq!(scope.introduce("main".into(), region(0)), Ok(symbols[0]));
+        assert_eq!(scope.introduce("helper".into(), region(10)), Ok(symbols[1]));
 
-        debug_assert_eq!(id.index(), self.in_scope.len());
-        debug_assert_eq!(id.index(), self.regions.len());
+        // but a second definition is still a shadow
+        let (original, _, _) = scope.introduce("helper".into(), region(20)).unwrap_err();
+        assert_eq!(original, Loc::at(region(10), symbols[1]));
+    }
 
-        self.in_scope.push(true);
-        self.regions.push(region);
+    #[test]
+    fn pre_introduce_top_level_shadowed_in_inner_scope() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
 
-        id
-    }
+        let region = |offset| Region::from_pos(Position { offset });
 
-    /// Adds an IdentId, but does not introduce it to the scope
-    fn scopeless_symbol(&mut self, ident_name: &str, region: Region) -> Symbol {
-        let id = self.ident_ids.add_str(ident_name);
+        let (symbols, _) = scope.pre_introduce_top_level(&[("x".into(), region(0))]);
 
-        debug_assert_eq!(id.index(), self.in_scope.len());
-        debug_assert_eq!(id.index(), self.regions.len());
+        // e.g. `x = ...` in a closure, before the top-level definition of `x`
+        scope.inner_scope(|inner| {
+            let (original, shadow, shadow_symbol) =
+                inner.introduce("x".into(), region(10)).unwrap_err();
 
-        self.in_scope.push(false);
-        self.regions.push(region);
+            assert_eq!(original, Loc::at(region(0), symbols[0]));
+            assert_eq!(shadow, Loc::at(region(10), "x".into()));
+            assert_ne!(shadow_symbol, symbols[0]);
+        });
 
-        Symbol::new(self.home, id)
+        // the top-level definition still gets the pre-introduced symbol
+        assert_eq!(scope.introduce("x".into(), region(0)), Ok(symbols[0]));
     }
 
-    fn gen_unique(&mut self) -> IdentId {
-        let id = self.ident_ids.gen_unique();
+    #[test]
+    fn pre_introduce_top_level_duplicate() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
 
-        debug_assert_eq!(id.index(), self.in_scope.len());
-        debug_assert_eq!(id.index(), self.regions.len());
+        let region = |offset| Region::from_pos(Position { offset });
 
-        self.in_scope.push(false);
-        self.regions.push(Region::zero());
+        let (symbols, conflicts) = scope.pre_introduce_top_level(&[
+            ("total".into(), region(0)),
+            ("count".into(), region(10)),
+            ("total".into(), region(20)),
+        ]);
 
-        id
+        assert_eq!(
+            conflicts,
+            vec![GroupConflict::Duplicate {
+                original: Loc::at(region(0), "total".into()),
+                duplicate: Loc::at(region(20), "total".into()),
+            }]
+        );
+        assert_ne!(symbols[0], symbols[2]);
+
+        assert_eq!(scope.lookup(&"total".into(), region(30)), Ok(symbols[0]));
+        assert_eq!(scope.introduce("total".into(), region(0)), Ok(symbols[0]));
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use roc_module::symbol::ModuleIds;
-    use roc_region::all::Position;
+    fn list_alias(var_store: &mut VarStore) -> (Alias, Variable) {
+        let var = var_store.fresh();
+        let typ = Type::Apply(
+            Symbol::LIST_LIST,
+            vec![Loc::at_zero(Type::Variable(var))],
+            Region::zero(),
+        );
+        let vars = vec![Loc::at_zero(AliasVar::unbound("a".into(), var))];
+        let alias = create_alias(
+            Symbol::LIST_LIST,
+            Region::zero(),
+            vars,
+            vec![],
+            typ,
+            AliasKind::Structural,
+        );
 
-    use pretty_assertions::{assert_eq, assert_ne};
+        (alias, var)
+    }
 
     #[test]
-    fn scope_contains_introduced() {
+    fn instantiate_alias() {
         let _register_module_debug_names = ModuleIds::default();
-        let mut scope = Scope::new(
-            ModuleId::ATTR,
-            IdentIds::default(),
-            PendingAbilitiesStore::default(),
-        );
-
-        let region = Region::zero();
-        let ident = Ident::from("mezolit");
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+        let mut var_store = VarStore::default();
 
-        assert!(scope.lookup(&ident, region).is_err());
+        let (alias, _) = list_alias(&mut var_store);
+        scope.aliases.insert(Symbol::LIST_LIST, alias);
 
-        assert!(scope.introduce(ident.clone(), region).is_ok());
+        let arg = Type::Apply(Symbol::STR_STR, vec![], Region::zero());
+        let expected = Type::Apply(
+            Symbol::LIST_LIST,
+            vec![Loc::at_zero(arg.clone())],
+            Region::zero(),
+        );
 
-        assert!(scope.lookup(&ident, region).is_ok());
+        assert_eq!(
+            scope.instantiate_alias(Symbol::LIST_LIST, &[arg]),
+            Ok(expected)
+        );
     }
 
     #[test]
-    fn second_introduce_shadows() {
+    fn instantiate_alias_wrong_arity() {
         let _register_module_debug_names = ModuleIds::default();
-        let mut scope = Scope::new(
-            ModuleId::ATTR,
-            IdentIds::default(),
-            PendingAbilitiesStore::default(),
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+        let mut var_store = VarStore::default();
+
+        let (alias, var) = list_alias(&mut var_store);
+        scope.aliases.insert(Symbol::LIST_LIST, alias);
+
+        assert_eq!(
+            scope.instantiate_alias(Symbol::LIST_LIST, &[]),
+            Err(AliasInstantiationError::WrongArity {
+                symbol: Symbol::LIST_LIST,
+                expected: 1,
+                actual: 0,
+            })
         );
 
-        let region1 = Region::from_pos(Position { offset: 10 });
-        let region2 = Region::from_pos(Position { offset: 20 });
-        let ident = Ident::from("mezolit");
+        assert_eq!(
+            scope.instantiate_alias(Symbol::LIST_LIST, &[Type::EmptyRec, Type::EmptyRec]),
+            Err(AliasInstantiationError::WrongArity {
+                symbol: Symbol::LIST_LIST,
+                expected: 1,
+                actual: 2,
+            })
+        );
 
-        assert!(scope.lookup(&ident, Region::zero()).is_err());
+        // the alias in scope is left untouched
+        assert_eq!(
+            scope.instantiate_alias(Symbol::LIST_LIST, &[Type::Variable(var)]),
+            Ok(scope.lookup_alias(Symbol::LIST_LIST).unwrap().typ.clone())
+        );
 
-        let first = scope.introduce(ident.clone(), region1).unwrap();
-        let (original, _ident, shadow_symbol) =
-            scope.introduce(ident.clone(), region2).unwrap_err();
+        assert_eq!(
+            scope.instantiate_alias(Symbol::DICT_DICT, &[]),
+            Err(AliasInstantiationError::NotInScope(Symbol::DICT_DICT))
+        );
+    }
 
-        scope.register_debug_idents();
+    mod model {
+        use super::*;
+        use pretty_assertions::assert_eq;
+        use proptest::prelude::*;
+        use std::collections::HashMap;
+
+        const NAMES: &[&str] = &["a", "b", "concat", "len", "sum"];
+
+        const IMPORTS: &[Symbol] = &[
+            Symbol::LIST_APPEND,
+            Symbol::LIST_PRODUCT,
+            Symbol::STR_CONCAT,
+            Symbol::LIST_LEN,
+            Symbol::LIST_SUM,
+        ];
+
+        #[derive(Debug, Clone)]
+        enum Op {
+            Introduce(usize),
+            Import(usize),
+            Scopeless(usize),
+            /// Run the next `n` operations in an inner scope, and retract their locals afterwards
+            Inner(usize),
+        }
 
-        assert_ne!(first, shadow_symbol);
-        assert_eq!(original.region, region1);
+        fn op() -> impl Strategy<Value = Op> {
+            prop_oneof![
+                (0..NAMES.len()).prop_map(Op::Introduce),
+                (0..NAMES.len()).prop_map(Op::Import),
+                (0..NAMES.len()).prop_map(Op::Scopeless),
+                (1..5usize).prop_map(Op::Inner),
+            ]
+        }
 
-        let lookup = scope.lookup(&ident, Region::zero()).unwrap();
+        /// A naive model of the scope: imports are never retracted, locals are per inner scope
+        struct Model {
+            imports: HashMap<&'static str, Symbol>,
+            symbols: Vec<Symbol>,
+        }
 
-        assert_eq!(first, lookup);
-    }
+        impl Model {
+            fn lookup(&self, locals: &HashMap<&str, Symbol>, name: &str) -> Option<Symbol> {
+                self.imports.get(name).or_else(|| locals.get(name)).copied()
+            }
 
-    #[test]
-    fn inner_scope_does_not_influence_outer() {
-        let _register_module_debug_names = ModuleIds::default();
-        let mut scope = Scope::new(
-            ModuleId::ATTR,
-            IdentIds::default(),
-            PendingAbilitiesStore::default(),
-        );
+            fn fresh(&mut self, symbol: Symbol) {
+                assert_eq!(symbol.module_id(), ModuleId::ATTR);
+                assert!(!self.symbols.contains(&symbol), "{:?} reused", symbol);
 
-        let region = Region::zero();
-        let ident = Ident::from("uránia");
+                self.symbols.push(symbol);
+            }
+        }
 
-        assert!(scope.lookup(&ident, region).is_err());
+        fn run(
+            scope: &mut Scope,
+            model: &mut Model,
+            mut locals: HashMap<&'static str, Symbol>,
+            ops: &mut dyn Iterator<Item = Op>,
+        ) {
+            while let Some(op) = ops.next() {
+                match op {
+                    Op::Introduce(index) => {
+                        let name = NAMES[index];
+
+                        match scope.introduce(name.into(), Region::zero()) {
+                            Ok(symbol) => {
+                                assert_eq!(model.lookup(&locals, name), None);
+
+                                model.fresh(symbol);
+                                locals.insert(name, symbol);
+                            }
+                            Err((original, _, shadow)) => {
+                                assert_eq!(model.lookup(&locals, name), Some(original.value));
+
+                                model.fresh(shadow);
+                            }
+                        }
+                    }
+                    Op::Import(index) => {
+                        let name = NAMES[index];
+                        let result = scope.import(name.into(), IMPORTS[index], Region::zero());
 
-        scope.inner_scope(|inner| {
-            assert!(inner.introduce(ident.clone(), region).is_ok());
-        });
+                        assert_eq!(result.is_ok(), !model.imports.contains_key(name));
 
-        assert!(scope.lookup(&ident, region).is_err());
+                        model.imports.insert(name, IMPORTS[index]);
+                    }
+                    Op::Scopeless(index) => {
+                        let symbol = scope.scopeless_symbol(&NAMES[index].into(), Region::zero());
+
+                        model.fresh(symbol);
+                    }
+                    Op::Inner(n) => {
+                        let mut inner_ops = ops.take(n).collect::<Vec<_>>().into_iter();
+                        let inner_locals = locals.clone();
+
+                        scope.inner_scope(|inner| run(inner, model, inner_locals, &mut inner_ops));
+                    }
+                }
+
+                #[cfg(test)]
+                scope.check_invariants();
+
+                for name in NAMES {
+                    let lookup = scope.lookup(&Ident::from(*name), Region::zero()).ok();
+
+                    assert_eq!(lookup, model.lookup(&locals, name), "lookup of {}", name);
+                }
+            }
+        }
+
+        proptest! {
+            #[test]
+            fn scope_matches_model(ops in proptest::collection::vec(op(), 0..50)) {
+                let _register_module_debug_names = ModuleIds::default();
+                let mut scope = Scope::new_empty(ModuleId::ATTR);
+                let mut model = Model {
+                    imports: HashMap::new(),
+                    symbols: Vec::new(),
+                };
+
+                run(&mut scope, &mut model, HashMap::new(), &mut ops.into_iter());
+            }
+        }
     }
 
     #[test]
-    fn default_idents_in_scope() {
+    fn check_exposed_all_defined() {
         let _register_module_debug_names = ModuleIds::default();
-        let scope = Scope::new(
-            ModuleId::ATTR,
-            IdentIds::default(),
-            PendingAbilitiesStore::default(),
-        );
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
 
-        let idents: Vec<_> = scope.idents_in_scope().collect();
+        scope.introduce("foo".into(), Region::zero()).unwrap();
+        scope.introduce("bar".into(), Region::zero()).unwrap();
 
-        assert_eq!(
-            &idents,
-            &[
-                Ident::from("Str"),
-                Ident::from("List"),
-                Ident::from("Box"),
-                Ident::from("Ok"),
-                Ident::from("Err"),
-            ]
-        );
+        let exposed = [
+            Loc::at(Region::zero(), Ident::from("bar")),
+            Loc::at(Region::zero(), Ident::from("foo")),
+        ];
+
+        assert!(scope.check_exposed(&exposed).is_empty());
     }
 
     #[test]
-    fn idents_with_inner_scope() {
+    fn check_exposed_missing() {
         let _register_module_debug_names = ModuleIds::default();
-        let mut scope = Scope::new(
-            ModuleId::ATTR,
-            IdentIds::default(),
-            PendingAbilitiesStore::default(),
-        );
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
 
-        let idents: Vec<_> = scope.idents_in_scope().collect();
+        let region = |offset| Region::from_pos(Position { offset });
 
-        assert_eq!(
-            &idents,
-            &[
-                Ident::from("Str"),
-                Ident::from("List"),
-                Ident::from("Box"),
-                Ident::from("Ok"),
-                Ident::from("Err"),
-            ]
-        );
+        scope.introduce("foo".into(), region(100)).unwrap();
 
-        let builtin_count = idents.len();
+        let exposed = [
+            Loc::at(region(10), Ident::from("foo")),
+            Loc::at(region(20), Ident::from("baz")),
+        ];
 
-        let region = Region::zero();
+        match scope.check_exposed(&exposed).as_slice() {
+            [RuntimeError::LookupNotInScope(loc, suggestions)] => {
+                assert_eq!(loc.region, region(20));
+                assert_eq!(loc.value, Ident::from("baz"));
+                assert!(suggestions.contains("foo"));
+            }
+            other => panic!("expected one LookupNotInScope, got {:?}", other),
+        }
+    }
 
-        let ident1 = Ident::from("uránia");
-        let ident2 = Ident::from("malmok");
-        let ident3 = Ident::from("Járnak");
+    #[test]
+    fn validate_exposes() {
+        let _register_module_debug_names = ModuleIds::default();
+        let scope = Scope::new_empty(ModuleId::ATTR);
 
-        scope.introduce(ident1.clone(), region).unwrap();
-        scope.introduce(ident2.clone(), region).unwrap();
-        scope.introduce(ident3.clone(), region).unwrap();
+        let region = |offset| Region::from_pos(Position { offset });
 
-        let idents: Vec<_> = scope.idents_in_scope().collect();
+        let unique = [
+            Loc::at(region(10), Ident::from("foo")),
+            Loc::at(region(20), Ident::from("bar")),
+        ];
+
+        assert!(scope.validate_exposes(&unique).is_empty());
+
+        let duplicated = [
+            Loc::at(region(10), Ident::from("foo")),
+            Loc::at(region(20), Ident::from("bar")),
+            Loc::at(region(30), Ident::from("foo")),
+        ];
 
         assert_eq!(
-            &idents[builtin_count..],
-            &[ident1.clone(), ident2.clone(), ident3.clone(),]
+            scope.validate_exposes(&duplicated),
+            vec![(Ident::from("foo"), region(10), region(30))]
         );
+    }
 
-        scope.inner_scope(|inner| {
-            let ident4 = Ident::from("Ångström");
-            let ident5 = Ident::from("Sirály");
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "invalidated by removing from the scope")]
+    fn rollback_to_snapshot_of_cleared_scope() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+        let mut var_store = VarStore::default();
 
-            inner.introduce(ident4.clone(), region).unwrap();
-            inner.introduce(ident5.clone(), region).unwrap();
+        let (alias, _) = list_alias(&mut var_store);
+        scope.aliases_mut().insert(Symbol::LIST_LIST, alias);
 
-            let idents: Vec<_> = inner.idents_in_scope().collect();
+        let snapshot = scope.snapshot();
+        scope.take_aliases();
 
-            assert_eq!(
-                &idents[builtin_count..],
-                &[
-                    ident1.clone(),
-                    ident2.clone(),
-                    ident3.clone(),
-                    ident4,
-                    ident5
-                ]
-            );
-        });
+        scope.rollback_to(snapshot);
+    }
 
-        let idents: Vec<_> = scope.idents_in_scope().collect();
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "snapshot of a different scope")]
+    fn rollback_to_snapshot_of_other_scope() {
+        let _register_module_debug_names = ModuleIds::default();
+        let other = Scope::new_empty(ModuleId::ATTR);
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
 
-        assert_eq!(&idents[builtin_count..], &[ident1, ident2, ident3,]);
+        let snapshot = other.snapshot();
+
+        scope.rollback_to(snapshot);
     }
 
     #[test]
-    fn import_is_in_scope() {
+    fn dump() {
         let _register_module_debug_names = ModuleIds::default();
-        let mut scope = Scope::new(
-            ModuleId::ATTR,
-            IdentIds::default(),
-            PendingAbilitiesStore::default(),
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = |offset| Region::from_pos(Position { offset });
+
+        scope.introduce("total".into(), region(10)).unwrap();
+        let age = scope.introduce("Age".into(), region(20)).unwrap();
+        scope.add_alias(
+            age,
+            region(20),
+            vec![],
+            vec![],
+            Type::EmptyTagUnion,
+            AliasKind::Opaque,
         );
 
-        let ident = Ident::from("product");
-        let symbol = Symbol::LIST_PRODUCT;
-        let region = Region::zero();
+        let hoist = scope.introduce("Hoist".into(), region(30)).unwrap();
+        let lift = scope.introduce("lift".into(), region(40)).unwrap();
+        scope.abilities_store.register_ability(
+            hoist,
+            [(
+                lift,
+                AbilityMemberData {
+                    parent_ability: hoist,
+                    region: region(40),
+                    typ: PendingMemberType::Imported,
+                },
+            )],
+        );
+        scope
+            .introduce_or_shadow_ability_member(&VecMap::default(), "lift".into(), region(50))
+            .unwrap();
 
-        assert!(scope.lookup(&ident, region).is_err());
+        scope.introduce("count".into(), region(60)).unwrap();
 
-        assert!(scope.import(ident.clone(), symbol, region).is_ok());
+        assert_eq!(
+            scope.dump(),
+            indoc::indoc!(
+                r#"
+                bindings:
+                    Age Age#1 @20-21
+                    Hoist Hoist#2 @30-31
+                    count count#5 @60-61
+                    lift lift#3 @40-41
+                    total total#0 @10-11
+                aliases:
+                    Age#1 Opaque
+                abilities:
+                    Hoist#2: lift#3
+                specializations:
+                    lift#3 -> lift#4 @50-51
+                "#
+            )
+        );
+    }
 
-        assert!(scope.lookup(&ident, region).is_ok());
+    #[test]
+    fn ability_of_member() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let hoist = scope.introduce("Hoist".into(), Region::zero()).unwrap();
+        let lift = scope.introduce("lift".into(), Region::zero()).unwrap();
+        let listing = scope.introduce("listing".into(), Region::zero()).unwrap();
+        scope.abilities_store.register_ability(
+            hoist,
+            [(
+                lift,
+                AbilityMemberData {
+                    parent_ability: hoist,
+                    region: Region::zero(),
+                    typ: PendingMemberType::Imported,
+                },
+            )],
+        );
 
-        assert!(scope.idents_in_scope().any(|x| x == ident));
+        assert_eq!(scope.ability_of_member(lift), Some(hoist));
+        assert_eq!(scope.ability_of_member(listing), None);
+        assert_eq!(scope.ability_of_member(hoist), None);
     }
 
     #[test]
-    fn shadow_of_import() {
+    fn alias_dependencies() {
         let _register_module_debug_names = ModuleIds::default();
-        let mut scope = Scope::new(
-            ModuleId::ATTR,
-            IdentIds::default(),
-            PendingAbilitiesStore::default(),
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let inner = scope.introduce("Inner".into(), Region::zero()).unwrap();
+        let middle = scope.introduce("Middle".into(), Region::zero()).unwrap();
+        let outer = scope.introduce("Outer".into(), Region::zero()).unwrap();
+
+        let refer_to = |symbol| {
+            Type::DelayedAlias(AliasCommon {
+                symbol,
+                type_arguments: vec![],
+                lambda_set_variables: vec![],
+                infer_ext_in_output_types: vec![],
+            })
+        };
+
+        // Outer : { x : Middle, y : Str }, Middle : [A Inner, B Middle], Inner : []
+        let mut fields = SendMap::default();
+        fields.insert("x".into(), RecordField::Required(refer_to(middle)));
+        fields.insert(
+            "y".into(),
+            RecordField::Required(Type::Apply(Symbol::STR_STR, vec![], Region::zero())),
+        );
+        let outer_type = Type::Record(fields, TypeExtension::Closed);
+        let middle_type = Type::TagUnion(
+            vec![
+                ("A".into(), vec![refer_to(inner)]),
+                ("B".into(), vec![refer_to(middle)]),
+            ],
+            TypeExtension::Closed,
         );
 
-        let ident = Ident::from("product");
-        let symbol = Symbol::LIST_PRODUCT;
+        for (symbol, typ) in [
+            (outer, outer_type),
+            (middle, middle_type),
+            (inner, Type::EmptyTagUnion),
+        ] {
+            scope.add_alias(
+                symbol,
+                Region::zero(),
+                vec![],
+                vec![],
+                typ,
+                AliasKind::Structural,
+            );
+        }
 
-        let region1 = Region::from_pos(Position { offset: 10 });
-        let region2 = Region::from_pos(Position { offset: 20 });
+        assert_eq!(
+            scope.alias_dependencies(),
+            vec![
+                (outer, vec![middle]),
+                (middle, vec![inner]),
+                (inner, vec![])
+            ]
+        );
+    }
 
-        scope.import(ident.clone(), symbol, region1).unwrap();
+    #[test]
+    fn introduce_with_id() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        // allocated up front, like the top-level names of a module
+        let first = scope.locals.ident_ids.add_str("first");
+        let second = scope.locals.ident_ids.add_str("second");
+        scope.locals.in_scope.resize(2, false);
+        scope.locals.regions.resize(2, Region::zero());
+
+        let symbol = scope
+            .introduce_with_id("second".into(), second, Region::zero())
+            .unwrap();
+        assert_eq!(symbol, Symbol::new(ModuleId::ATTR, second));
+        assert_eq!(scope.lookup(&"second".into(), Region::zero()), Ok(symbol));
+        assert_eq!(scope.locals.ident_ids.len(), 2);
+
+        let symbol = scope
+            .introduce_with_id("first".into(), first, Region::zero())
+            .unwrap();
+        assert_eq!(symbol.ident_id(), first);
+
+        assert!(scope
+            .introduce_with_id("first".into(), first, Region::zero())
+            .is_err());
+    }
 
-        let (original, _ident, shadow_symbol) =
-            scope.introduce(ident.clone(), region2).unwrap_err();
+    #[test]
+    fn copy_alias() {
+        let _register_module_debug_names = ModuleIds::default();
+        let mut scope = Scope::new_empty(ModuleId::ATTR);
+
+        let region = |start, end| Region::new(Position::new(start), Position::new(end));
+
+        let age = scope.introduce("Age".into(), region(0, 10)).unwrap();
+        let specialized = scope
+            .introduce("SpecializedAge".into(), region(20, 30))
+            .unwrap();
+        let missing = scope.introduce("Missing".into(), region(40, 50)).unwrap();
+
+        scope.add_alias(
+            age,
+            region(0, 10),
+            vec![],
+            vec![],
+            Type::EmptyRec,
+            AliasKind::Opaque,
+        );
 
-        scope.register_debug_idents();
+        assert_eq!(scope.copy_alias(age, specialized, region(20, 30)), Ok(()));
 
-        assert_ne!(symbol, shadow_symbol);
-        assert_eq!(original.region, region1);
+        let original = scope.lookup_alias(age).unwrap();
+        assert_eq!(original.region, region(0, 10));
 
-        let lookup = scope.lookup(&ident, Region::zero()).unwrap();
+        let copy = scope.lookup_alias(specialized).unwrap();
+        assert_eq!(copy.region, region(20, 30));
+        assert_eq!(copy.kind, AliasKind::Opaque);
+        assert!(matches!(copy.typ, Type::EmptyRec));
 
-        assert_eq!(symbol, lookup);
+        assert_eq!(
+            scope.copy_alias(missing, specialized, region(40, 50)),
+            Err(AliasNotFound(missing))
+        );
+        assert!(scope.lookup_alias(missing).is_none());
     }
 }
@@ -0,0 +1,40 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use roc_collections::{MutSet, VecSet};
+
+// sets like an alias's recursion variables almost always hold zero or one element
+const SIZES: [u32; 5] = [0, 1, 2, 3, 4];
+
+pub fn small_sets_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build and query small set");
+
+    for size in SIZES {
+        group.bench_with_input(BenchmarkId::new("VecSet", size), &size, |b, &size| {
+            b.iter(|| {
+                let mut set = VecSet::default();
+
+                for value in 0..size {
+                    set.insert(black_box(value));
+                }
+
+                black_box(set.contains(&black_box(size / 2)))
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("MutSet", size), &size, |b, &size| {
+            b.iter(|| {
+                let mut set = MutSet::default();
+
+                for value in 0..size {
+                    set.insert(black_box(value));
+                }
+
+                black_box(set.contains(&black_box(size / 2)))
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, small_sets_benchmark);
+criterion_main!(benches);
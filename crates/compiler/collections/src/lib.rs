@@ -13,5 +13,5 @@ mod vec_set;
 pub use all::{default_hasher, BumpMap, ImEntry, ImMap, ImSet, MutMap, MutSet, SendMap};
 pub use reference_matrix::{ReferenceMatrix, Sccs, TopologicalSort};
 pub use small_string_interner::SmallStringInterner;
-pub use vec_map::VecMap;
+pub use vec_map::{Entry as VecMapEntry, VecMap};
 pub use vec_set::VecSet;
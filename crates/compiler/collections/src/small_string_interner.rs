@@ -255,6 +255,49 @@ impl SmallStringInterner {
     pub fn is_empty(&self) -> bool {
         self.lengths.is_empty()
     }
+
+    /// The number of strings that fit without reallocating the length and offset tables
+    pub fn capacity(&self) -> usize {
+        self.lengths.capacity().min(self.offsets.capacity())
+    }
+
+    /// Panics if `lengths` and `offsets` are out of sync, or if an entry does not point at
+    /// valid UTF-8 within `buffer`.
+    #[cfg(debug_assertions)]
+    pub fn check_invariants(&self) {
+        assert_eq!(
+            self.lengths.len(),
+            self.offsets.len(),
+            "lengths and offsets of the interner are out of sync"
+        );
+
+        for (index, (length, offset)) in self.lengths.iter().zip(&self.offsets).enumerate() {
+            let length = match length.kind() {
+                Kind::Empty => continue,
+                Kind::Generated(length) | Kind::Interned(length) => length,
+            };
+
+            let bytes = self
+                .buffer
+                .get(*offset as usize..)
+                .and_then(|rest| rest.get(..length));
+
+            match bytes {
+                None => panic!(
+                    "interner entry {} ({}..{}) is out of bounds of its buffer of {} bytes",
+                    index,
+                    offset,
+                    *offset as usize + length,
+                    self.buffer.len()
+                ),
+                Some(bytes) => assert!(
+                    std::str::from_utf8(bytes).is_ok(),
+                    "interner entry {} is not valid UTF-8",
+                    index
+                ),
+            }
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -353,6 +396,18 @@ mod test {
         assert!(interner.find_and_update("c", "cd").is_some());
     }
 
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "out of sync")]
+    fn invariants_catch_missing_offset() {
+        let mut interner = SmallStringInterner::default();
+
+        interner.insert("main");
+        interner.offsets.pop();
+
+        interner.check_invariants();
+    }
+
     #[cfg(all(
         target_arch = "x86_64",
         target_feature = "avx",
@@ -118,6 +118,14 @@ impl<T> Slice<T> {
         Self::new(start, (end - start) as u16)
     }
 
+    /// Whether the elements of this slice of `buffer` are equal to `other`
+    pub fn eq_in(&self, buffer: &[T], other: &[T]) -> bool
+    where
+        T: PartialEq,
+    {
+        &buffer[self.indices()] == other
+    }
+
     pub const fn len(&self) -> usize {
         self.length as _
     }
@@ -191,3 +199,19 @@ impl<T, U> EitherIndex<T, U> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::Slice;
+
+    #[test]
+    fn eq_in() {
+        let buffer = vec![1, 2, 3, 4];
+        let slice = Slice::<i32>::new(1, 2);
+
+        assert!(slice.eq_in(&buffer, &[2, 3]));
+        assert!(!slice.eq_in(&buffer, &[2, 4]));
+        assert!(!slice.eq_in(&buffer, &[2]));
+        assert!(Slice::<i32>::empty().eq_in(&buffer, &[]));
+    }
+}
@@ -52,6 +52,12 @@ impl<K: PartialEq, V> VecMap<K, V> {
         self.keys.is_empty()
     }
 
+    /// Reserves capacity for at least `additional` more key/value pairs.
+    pub fn reserve(&mut self, additional: usize) {
+        self.keys.reserve(additional);
+        self.values.reserve(additional);
+    }
+
     pub fn insert(&mut self, key: K, mut value: V) -> Option<V> {
         match self.keys.iter().position(|x| x == &key) {
             Some(index) => {
@@ -105,6 +111,15 @@ impl<K: PartialEq, V> VecMap<K, V> {
         }
     }
 
+    /// Gets the entry for `key`, so it can be inspected and then filled or updated
+    /// without looking the key up a second time.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        match self.keys.iter().position(|x| x == &key) {
+            Some(index) => Entry::Occupied(OccupiedEntry { map: self, index }),
+            None => Entry::Vacant(VacantEntry { map: self, key }),
+        }
+    }
+
     pub fn iter(&self) -> impl ExactSizeIterator<Item = (&K, &V)> {
         self.keys.iter().zip(self.values.iter())
     }
@@ -286,6 +301,168 @@ where
 {
 }
 
+pub enum Entry<'a, K, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+impl<'a, K, V> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// The existing value, or the result of `default` if the key was not yet in the map.
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Sets the value of the entry, replacing any existing value.
+    pub fn insert(self, value: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(mut entry) => {
+                entry.insert(value);
+                entry.into_mut()
+            }
+            Entry::Vacant(entry) => entry.insert(value),
+        }
+    }
+}
+
+pub struct OccupiedEntry<'a, K, V> {
+    map: &'a mut VecMap<K, V>,
+    index: usize,
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.map.keys[self.index]
+    }
+
+    pub fn get(&self) -> &V {
+        &self.map.values[self.index]
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.values[self.index]
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.values[self.index]
+    }
+
+    /// Replaces the value, returning the old one. The original key is kept.
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> (K, V) {
+        self.map.swap_remove(self.index)
+    }
+}
+
+pub struct VacantEntry<'a, K, V> {
+    map: &'a mut VecMap<K, V>,
+    key: K,
+}
+
+impl<'a, K, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.map.keys.push(self.key);
+        self.map.values.push(value);
+
+        self.map.values.last_mut().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test_entry {
+    use crate::{VecMap, VecMapEntry as Entry};
+
+    #[test]
+    fn vacant_then_occupied() {
+        let mut map: VecMap<u32, &str> = VecMap::default();
+
+        match map.entry(1) {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), &1);
+                *entry.insert("one") = "uno";
+            }
+            Entry::Occupied(_) => panic!("empty map has no occupied entries"),
+        }
+
+        match map.entry(1) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.get(), &"uno");
+                assert_eq!(entry.insert("one"), "uno");
+            }
+            Entry::Vacant(_) => panic!("key was just inserted"),
+        }
+
+        assert_eq!(map.get(&1), Some(&"one"));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn or_insert_with_keeps_existing() {
+        let mut map = VecMap::default();
+        map.insert(1, 10);
+
+        *map.entry(1).or_insert_with(|| unreachable!()) += 1;
+        *map.entry(2).or_insert_with(|| 20) += 1;
+
+        assert_eq!(map.get(&1), Some(&11));
+        assert_eq!(map.get(&2), Some(&21));
+    }
+
+    #[test]
+    fn insert_replaces() {
+        let mut map = VecMap::default();
+        map.insert(1, 10);
+
+        assert_eq!(*map.entry(1).insert(11), 11);
+        assert_eq!(*map.entry(2).insert(20), 20);
+
+        let mut pairs: Vec<_> = map.into_iter().collect();
+        pairs.sort_unstable();
+        assert_eq!(pairs, vec![(1, 11), (2, 20)]);
+    }
+
+    #[test]
+    fn occupied_remove() {
+        let mut map = VecMap::default();
+        map.extend(vec![(1, 10), (2, 20), (3, 30)]);
+
+        match map.entry(1) {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), (1, 10)),
+            Entry::Vacant(_) => panic!("key is in the map"),
+        }
+
+        assert!(!map.contains_key(&1));
+        assert_eq!(map.get(&3), Some(&30));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn reserve() {
+        let mut map: VecMap<u32, u32> = VecMap::default();
+        map.reserve(8);
+
+        let (keys, values) = map.unzip();
+        assert!(keys.capacity() >= 8);
+        assert!(values.capacity() >= 8);
+    }
+}
+
 #[cfg(test)]
 mod test_drain_filter {
     use crate::VecMap;
@@ -153,3 +153,52 @@ impl<T> From<Vec<T>> for VecSet<T> {
         Self { elements }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::VecSet;
+
+    #[test]
+    fn insert_contains_remove() {
+        let mut set = VecSet::default();
+        assert!(set.is_empty());
+
+        // insert reports whether the value was already present
+        assert!(!set.insert(3));
+        assert!(!set.insert(1));
+        assert!(set.insert(3));
+        assert_eq!(set.len(), 2);
+
+        assert!(set.contains(&1));
+        assert!(set.contains(&3));
+        assert!(!set.contains(&2));
+
+        assert!(set.remove(&3));
+        assert!(!set.remove(&3));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![&1]);
+    }
+
+    #[test]
+    fn from_iter_sorts_and_dedups() {
+        let set: VecSet<_> = vec![4, 2, 4, 1, 2].into_iter().collect();
+        assert_eq!(set.into_vec(), vec![1, 2, 4]);
+
+        // small inputs skip the sort, but are still deduplicated
+        let set: VecSet<_> = [5, 5].into_iter().collect();
+        assert_eq!(set.into_vec(), vec![5]);
+
+        let set: VecSet<u32> = std::iter::empty().collect();
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn equality_is_order_sensitive() {
+        let a: VecSet<_> = vec![1, 2].into();
+        let b: VecSet<_> = vec![2, 1].into();
+        assert_ne!(a, b);
+
+        let c: VecSet<_> = vec![2, 1, 3].into_iter().collect();
+        let d: VecSet<_> = vec![3, 1, 2].into_iter().collect();
+        assert_eq!(c, d);
+    }
+}
@@ -330,6 +330,7 @@ fn start_phase<'a>(
 
                 let mut aliases = MutMap::default();
                 let mut abilities_store = PendingAbilitiesStore::default();
+                let mut exposed_by_imports = Vec::new();
 
                 for imported in parsed.imported_modules.keys() {
                     match state.module_cache.aliases.get(imported) {
@@ -370,6 +371,20 @@ fn start_phase<'a>(
                             // parallel, but we'll fill that in during type-checking our module.
                             abilities_store
                                 .union(import_store.closure_from_imported(exposed_symbols));
+
+                            // Remember the names exposed by the modules we import, so an
+                            // unqualified use of one of them can suggest the qualified name.
+                            // That includes the builtin modules every module imports, so that
+                            // e.g. `true` suggests `Bool.true`.
+                            if let Some(ident_ids) = dep_idents.get(imported) {
+                                let idents = exposed_symbols
+                                    .iter()
+                                    .filter_map(|symbol| ident_ids.get_name(symbol.ident_id()))
+                                    .map(Ident::from)
+                                    .collect();
+
+                                exposed_by_imports.push((*imported, idents));
+                            }
                         }
                     }
                 }
@@ -384,6 +399,7 @@ fn start_phase<'a>(
                     parsed,
                     dep_idents,
                     exposed_symbols,
+                    exposed_by_imports,
                     module_ids,
                     aliases,
                     abilities_store,
@@ -1213,6 +1229,7 @@ enum BuildTask<'a> {
         module_ids: ModuleIds,
         dep_idents: IdentIdsByModule,
         exposed_symbols: VecSet<Symbol>,
+        exposed_by_imports: Vec<(ModuleId, Vec<Ident>)>,
         aliases: MutMap<Symbol, Alias>,
         abilities_store: PendingAbilitiesStore,
         exposed_module_ids: &'a [ModuleId],
@@ -5319,6 +5336,7 @@ fn canonicalize_and_constrain<'a>(
     module_ids: &ModuleIds,
     dep_idents: IdentIdsByModule,
     exposed_symbols: VecSet<Symbol>,
+    exposed_by_imports: Vec<(ModuleId, Vec<Ident>)>,
     aliases: MutMap<Symbol, Alias>,
     imported_abilities_state: PendingAbilitiesStore,
     parsed: ParsedModule<'a>,
@@ -5346,7 +5364,7 @@ fn canonicalize_and_constrain<'a>(
     let parsed_defs = arena.alloc(parsed_defs);
 
     let mut var_store = VarStore::default();
-    let module_output = canonicalize_module_defs(
+    let mut module_output = canonicalize_module_defs(
         arena,
         parsed_defs,
         &header_type,
@@ -5358,6 +5376,7 @@ fn canonicalize_and_constrain<'a>(
         imported_abilities_state,
         exposed_imports,
         exposed_symbols,
+        exposed_by_imports,
         &symbols_from_requires,
         &mut var_store,
     );
@@ -5449,7 +5468,7 @@ fn canonicalize_and_constrain<'a>(
         .map(|(k, v)| (k, (true, v)))
         .collect();
 
-    for (name, alias) in module_output.scope.aliases {
+    for (name, alias) in module_output.scope.take_aliases() {
         match aliases.entry(name) {
             Occupied(_) => {
                 // do nothing
@@ -6299,6 +6318,7 @@ fn run_task<'a>(
             module_ids,
             dep_idents,
             exposed_symbols,
+            exposed_by_imports,
             aliases,
             abilities_store,
             skip_constraint_gen,
@@ -6309,6 +6329,7 @@ fn run_task<'a>(
                 &module_ids,
                 dep_idents,
                 exposed_symbols,
+                exposed_by_imports,
                 aliases,
                 abilities_store,
                 parsed,
@@ -688,6 +688,10 @@ impl IdentIds {
     pub fn is_empty(&self) -> bool {
         self.interner.is_empty()
     }
+
+    pub fn capacity(&self) -> usize {
+        self.interner.capacity()
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -1006,6 +1010,26 @@ macro_rules! define_builtins {
                 }
             }
 
+            /// The builtin type with the given name, if any, e.g. `Result` or `Str`.
+            pub fn builtin_type_named(name: &str) -> Option<Symbol> {
+                $(
+                    $(
+                        $(
+                            if $exposed_apply_type && $ident_name == name {
+                                return Some(Symbol::new(ModuleId::$module_const, IdentId($ident_id)));
+                            }
+                        )?
+                        $(
+                            if $exposed_type && $ident_name == name {
+                                return Some(Symbol::new(ModuleId::$module_const, IdentId($ident_id)));
+                            }
+                        )?
+                    )*
+                )+
+
+                None
+            }
+
             /// Symbols that should be added to the default scope, for hints as suggestions of
             /// names you might want to use.
             ///
@@ -24,6 +24,8 @@ pub enum BadPattern {
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ShadowKind {
     Variable,
+    /// A variable with the same name as a builtin value that was imported, by name or by default
+    Builtin(Symbol),
     Alias(Symbol),
     Opaque(Symbol),
     Ability(Symbol),
@@ -42,6 +44,12 @@ pub enum Problem {
     /// Second symbol is the name of the argument that is unused
     UnusedArgument(Symbol, bool, Symbol, Region),
     UnusedBranchDef(Symbol, Region),
+    /// A symbol that was marked as deprecated is used
+    DeprecatedLookup {
+        symbol: Symbol,
+        note: Box<str>,
+        region: Region,
+    },
     DefsOnlyUsedInRecursion(usize, Region),
     PrecedenceProblem(PrecedenceProblem),
     // Example: (5 = 1 + 2) is an unsupported pattern in an assignment; Int patterns aren't allowed in assignments!
@@ -218,6 +226,7 @@ impl Problem {
             Problem::UnknownGeneratesWith(_) => RuntimeError,
             Problem::UnusedArgument(_, _, _, _) => Warning,
             Problem::UnusedBranchDef(_, _) => Warning,
+            Problem::DeprecatedLookup { .. } => Warning,
             Problem::PrecedenceProblem(_) => RuntimeError,
             Problem::UnsupportedPattern(_, _) => RuntimeError,
             Problem::Shadowing { .. } => RuntimeError,
@@ -290,6 +299,7 @@ impl Problem {
             | Problem::UnknownGeneratesWith(Loc { region, .. })
             | Problem::UnusedArgument(_, _, _, region)
             | Problem::UnusedBranchDef(_, region)
+            | Problem::DeprecatedLookup { region, .. }
             | Problem::PrecedenceProblem(PrecedenceProblem::BothNonAssociative(region, _, _))
             | Problem::UnsupportedPattern(_, region)
             | Problem::CyclicAlias(_, region, _, _)
@@ -328,6 +338,18 @@ impl Problem {
             | Problem::RuntimeError(RuntimeError::UnsupportedPattern(region))
             | Problem::RuntimeError(RuntimeError::MalformedPattern(_, region))
             | Problem::RuntimeError(RuntimeError::LookupNotInScope(Loc { region, .. }, _))
+            | Problem::RuntimeError(RuntimeError::UsedBeforeDefinition {
+                usage: Loc { region, .. },
+                ..
+            })
+            | Problem::RuntimeError(RuntimeError::LookupNotInScopeButExposed {
+                usage: Loc { region, .. },
+                ..
+            })
+            | Problem::RuntimeError(RuntimeError::LookupNotInScopeButLooksAlike {
+                usage: Loc { region, .. },
+                ..
+            })
             | Problem::RuntimeError(RuntimeError::OpaqueNotDefined {
                 usage: Loc { region, .. },
                 ..
@@ -495,6 +517,25 @@ pub enum RuntimeError {
     ErroneousType,
 
     LookupNotInScope(Loc<Ident>, MutSet<Box<str>>),
+    /// An unqualified name is not in scope, but imported modules expose a value with that name
+    ///
+    /// e.g. `map` was written where `List.map` was meant
+    LookupNotInScopeButExposed {
+        usage: Loc<Ident>,
+        /// The (at most two) imported modules that expose this name
+        exposed_by: Vec<ModuleName>,
+    },
+    /// An unqualified name is not in scope, but a name that looks the same is; they are written
+    /// with different unicode characters, e.g. the `ﬁ` ligature instead of `fi`
+    LookupNotInScopeButLooksAlike {
+        usage: Loc<Ident>,
+        similar: Ident,
+    },
+    /// A name was used before the definition that introduces it
+    UsedBeforeDefinition {
+        usage: Loc<Ident>,
+        definition_region: Region,
+    },
     OpaqueNotDefined {
         usage: Loc<Ident>,
         opaques_in_scope: MutSet<Box<str>>,
@@ -0,0 +1,46 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use roc_module::symbol::Symbol;
+use roc_region::all::{Loc, Region};
+use roc_types::subs::VarStore;
+use roc_types::types::{Type, TypeExtension};
+
+/// A function type with `width` arguments, each a list nested `depth` deep around a record
+fn nested_type(var_store: &mut VarStore, width: usize, depth: usize) -> Type {
+    let args = (0..width)
+        .map(|_| {
+            let mut typ = Type::Record(
+                Default::default(),
+                TypeExtension::from_non_annotation_type(Type::Variable(var_store.fresh())),
+            );
+
+            for _ in 0..depth {
+                typ = Type::Apply(Symbol::LIST_LIST, vec![Loc::at_zero(typ)], Region::zero());
+            }
+
+            typ
+        })
+        .collect();
+
+    Type::Function(
+        args,
+        Box::new(Type::Variable(var_store.fresh())),
+        Box::new(Type::Variable(var_store.fresh())),
+    )
+}
+
+pub fn variables_detail_benchmark(c: &mut Criterion) {
+    let mut var_store = VarStore::default();
+
+    let wide = nested_type(&mut var_store, 1_000, 4);
+    c.bench_function("variables_detail wide", |b| {
+        b.iter(|| black_box(&wide).variables_detail())
+    });
+
+    let deep = nested_type(&mut var_store, 4, 1_000);
+    c.bench_function("variables_detail deep", |b| {
+        b.iter(|| black_box(&deep).variables_detail())
+    });
+}
+
+criterion_group!(benches, variables_detail_benchmark);
+criterion_main!(benches);
@@ -1068,6 +1068,37 @@ impl VarStore {
 
         Variable(answer)
     }
+
+    /// The current position of the store, to later [rollback_to](VarStore::rollback_to)
+    pub fn snapshot(&self) -> VarStoreSnapshot {
+        VarStoreSnapshot { next: self.next }
+    }
+
+    /// Hand out the variables created since `snapshot` again.
+    ///
+    /// Only valid when none of those variables are still in use anywhere, e.g. because
+    /// everything that was created with them has been discarded.
+    pub fn rollback_to(&mut self, snapshot: VarStoreSnapshot) {
+        debug_assert!(
+            snapshot.next <= self.next,
+            "rolling back to a snapshot that was taken later"
+        );
+
+        self.next = snapshot.next;
+    }
+}
+
+/// The state of a [VarStore] at some point, see [VarStore::snapshot]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VarStoreSnapshot {
+    next: u32,
+}
+
+impl VarStoreSnapshot {
+    /// Whether `var` was created after this snapshot was taken
+    pub fn is_newer(&self, var: Variable) -> bool {
+        var.0 >= self.next
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
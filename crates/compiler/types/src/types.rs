@@ -6,7 +6,7 @@ use crate::subs::{
 };
 use roc_collections::all::{HumanIndex, ImMap, ImSet, MutMap, MutSet, SendMap};
 use roc_collections::soa::{Index, Slice};
-use roc_collections::VecMap;
+use roc_collections::{VecMap, VecSet};
 use roc_error_macros::internal_error;
 use roc_module::called_via::CalledVia;
 use roc_module::ident::{ForeignSymbol, Lowercase, TagName};
@@ -3469,7 +3469,7 @@ fn variables_help(tipe: &Type, accum: &mut ImSet<Variable>) {
 pub struct VariableDetail {
     pub type_variables: MutSet<Variable>,
     pub lambda_set_variables: Vec<Variable>,
-    pub recursion_variables: MutSet<Variable>,
+    pub recursion_variables: VecSet<Variable>,
 }
 
 impl VariableDetail {
@@ -3480,138 +3480,150 @@ impl VariableDetail {
     }
 }
 
+/// Pending work of [variables_help_detailed]
+enum DetailWork<'a> {
+    Type(&'a Type),
+    /// A lambda set: a variable is a lambda set variable, any other type is traversed
+    LambdaSet(&'a Type),
+    /// Everything within the recursive tag union with this recursion variable has been traversed
+    Recursion(Variable),
+}
+
+/// Traverses `tipe` with an explicit stack rather than by recursion, because generated types can
+/// be nested deeply enough to overflow the stack.
 fn variables_help_detailed(tipe: &Type, accum: &mut VariableDetail) {
     use Type::*;
 
-    match tipe {
-        EmptyRec | EmptyTagUnion | Error => (),
+    fn push_ext<'a>(stack: &mut Vec<DetailWork<'a>>, ext: &'a TypeExtension) {
+        if let TypeExtension::Open(ext, _) = ext {
+            stack.push(DetailWork::Type(ext));
+        }
+    }
 
-        Variable(v) => {
-            accum.type_variables.insert(*v);
+    fn push_tags<'a>(stack: &mut Vec<DetailWork<'a>>, tags: &'a [(TagName, Vec<Type>)]) {
+        for (_, args) in tags.iter().rev() {
+            stack.extend(args.iter().rev().map(DetailWork::Type));
         }
+    }
 
-        Function(args, closure, ret) => {
-            for arg in args {
-                variables_help_detailed(arg, accum);
-            }
-            if let Type::Variable(v) = **closure {
-                accum.lambda_set_variables.push(v);
-            } else {
-                variables_help_detailed(closure, accum);
-            }
+    // work is popped from the end, so the parts of a type are pushed in reverse to traverse them
+    // in order; the order of the lambda set variables matters
+    let mut stack = vec![DetailWork::Type(tipe)];
 
-            variables_help_detailed(ret, accum);
-        }
-        Record(fields, ext) => {
-            for (_, field) in fields {
-                variables_help_detailed(field.as_inner(), accum);
+    while let Some(work) = stack.pop() {
+        let tipe = match work {
+            DetailWork::Type(tipe) => tipe,
+            DetailWork::LambdaSet(Type::Variable(v)) => {
+                accum.lambda_set_variables.push(*v);
+                continue;
             }
+            DetailWork::LambdaSet(tipe) => tipe,
+            DetailWork::Recursion(rec) => {
+                // just check that this is actually a recursive type
+                // debug_assert!(accum.type_variables.contains(rec));
 
-            if let TypeExtension::Open(ext, _) = ext {
-                variables_help_detailed(ext, accum);
-            }
-        }
-        Tuple(elems, ext) => {
-            for (_, elem) in elems.iter() {
-                variables_help_detailed(elem, accum);
+                // this rec var doesn't need to be in flex_vars or rigid_vars
+                accum.type_variables.remove(&rec);
+
+                accum.recursion_variables.insert(rec);
+                continue;
             }
+        };
 
-            if let TypeExtension::Open(ext, _) = ext {
-                variables_help_detailed(ext, accum);
+        match tipe {
+            EmptyRec | EmptyTagUnion | Error => (),
+
+            Variable(v) => {
+                accum.type_variables.insert(*v);
             }
-        }
-        ClosureTag {
-            name: _,
-            captures,
-            ambient_function: _,
-        } => {
-            for t in captures {
-                variables_help_detailed(t, accum);
+
+            Function(args, closure, ret) => {
+                stack.push(DetailWork::Type(ret));
+                stack.push(DetailWork::LambdaSet(closure));
+                stack.extend(args.iter().rev().map(DetailWork::Type));
             }
-        }
-        TagUnion(tags, ext) => {
-            for (_, args) in tags {
-                for x in args {
-                    variables_help_detailed(x, accum);
-                }
+            Record(fields, ext) => {
+                push_ext(&mut stack, ext);
+
+                // the iterator is not double-ended, so reverse after pushing
+                let start = stack.len();
+                stack.extend(
+                    fields
+                        .iter()
+                        .map(|(_, field)| DetailWork::Type(field.as_inner())),
+                );
+                stack[start..].reverse();
             }
+            Tuple(elems, ext) => {
+                push_ext(&mut stack, ext);
 
-            if let TypeExtension::Open(ext, _) = ext {
-                variables_help_detailed(ext, accum);
+                let start = stack.len();
+                stack.extend(elems.iter().map(|(_, elem)| DetailWork::Type(elem)));
+                stack[start..].reverse();
             }
-        }
-        FunctionOrTagUnion(_, _, ext) => {
-            if let TypeExtension::Open(ext, _) = ext {
-                variables_help_detailed(ext, accum);
+            ClosureTag {
+                name: _,
+                captures,
+                ambient_function: _,
+            } => {
+                stack.extend(captures.iter().rev().map(DetailWork::Type));
             }
-        }
-        UnspecializedLambdaSet {
-            unspecialized: Uls(var, _, _),
-        } => {
-            accum.type_variables.insert(*var);
-        }
-        RecursiveTagUnion(rec, tags, ext) => {
-            for (_, args) in tags {
-                for x in args {
-                    variables_help_detailed(x, accum);
-                }
+            TagUnion(tags, ext) => {
+                push_ext(&mut stack, ext);
+                push_tags(&mut stack, tags);
             }
-
-            if let TypeExtension::Open(ext, _) = ext {
-                variables_help_detailed(ext, accum);
+            FunctionOrTagUnion(_, _, ext) => {
+                push_ext(&mut stack, ext);
             }
-
-            // just check that this is actually a recursive type
-            // debug_assert!(accum.type_variables.contains(rec));
-
-            // this rec var doesn't need to be in flex_vars or rigid_vars
-            accum.type_variables.remove(rec);
-
-            accum.recursion_variables.insert(*rec);
-        }
-        DelayedAlias(AliasCommon {
-            type_arguments,
-            lambda_set_variables,
-            infer_ext_in_output_types: _,
-            ..
-        }) => {
-            for arg in type_arguments {
-                variables_help_detailed(&arg.value.typ, accum);
+            UnspecializedLambdaSet {
+                unspecialized: Uls(var, _, _),
+            } => {
+                accum.type_variables.insert(*var);
             }
-
-            for lambda_set in lambda_set_variables {
-                if let Type::Variable(v) = lambda_set.0 {
-                    accum.lambda_set_variables.push(v);
-                } else {
-                    variables_help_detailed(&lambda_set.0, accum);
-                }
+            RecursiveTagUnion(rec, tags, ext) => {
+                stack.push(DetailWork::Recursion(*rec));
+                push_ext(&mut stack, ext);
+                push_tags(&mut stack, tags);
             }
-        }
-        Alias {
-            type_arguments,
-            actual,
-            infer_ext_in_output_types: _,
-            ..
-        } => {
-            for arg in type_arguments {
-                variables_help_detailed(&arg.typ, accum);
+            DelayedAlias(AliasCommon {
+                type_arguments,
+                lambda_set_variables,
+                infer_ext_in_output_types: _,
+                ..
+            }) => {
+                stack.extend(
+                    (lambda_set_variables.iter().rev())
+                        .map(|lambda_set| DetailWork::LambdaSet(&lambda_set.0)),
+                );
+                stack.extend(
+                    (type_arguments.iter().rev()).map(|arg| DetailWork::Type(&arg.value.typ)),
+                );
             }
-            variables_help_detailed(actual, accum);
-        }
-        HostExposedAlias {
-            type_arguments: arguments,
-            actual,
-            ..
-        } => {
-            for arg in arguments {
-                variables_help_detailed(arg, accum);
+            Alias {
+                type_arguments,
+                actual,
+                infer_ext_in_output_types: _,
+                ..
+            } => {
+                stack.push(DetailWork::Type(actual));
+                stack.extend(
+                    type_arguments
+                        .iter()
+                        .rev()
+                        .map(|arg| DetailWork::Type(&arg.typ)),
+                );
             }
-            variables_help_detailed(actual, accum);
-        }
-        RangedNumber(_) => {}
-        Apply(_, args, _) => {
-            for x in args {
-                variables_help_detailed(&x.value, accum);
+            HostExposedAlias {
+                type_arguments: arguments,
+                actual,
+                ..
+            } => {
+                stack.push(DetailWork::Type(actual));
+                stack.extend(arguments.iter().rev().map(DetailWork::Type));
+            }
+            RangedNumber(_) => {}
+            Apply(_, args, _) => {
+                stack.extend(args.iter().rev().map(|x| DetailWork::Type(&x.value)));
             }
         }
     }
@@ -3889,7 +3901,8 @@ pub struct Alias {
     /// positions.
     pub infer_ext_in_output_variables: Vec<Variable>,
 
-    pub recursion_variables: MutSet<Variable>,
+    /// In practice there is at most one recursion variable, so a `VecSet` is cheaper than hashing
+    pub recursion_variables: VecSet<Variable>,
 
     pub typ: Type,
 
@@ -4902,4 +4915,59 @@ mod test {
             _ => panic!(),
         }
     }
+
+    #[test]
+    fn variables_detail_order() {
+        let mut var_store = VarStore::default();
+        let a = var_store.fresh();
+        let rec = var_store.fresh();
+        let l1 = var_store.fresh();
+        let l2 = var_store.fresh();
+
+        // (a -[l1]-> [Cons rec, Nil] as rec) -[l2]-> a
+        let inner = Type::Function(
+            vec![Type::Variable(a)],
+            Box::new(Type::Variable(l1)),
+            Box::new(Type::RecursiveTagUnion(
+                rec,
+                vec![
+                    (TagName("Cons".into()), vec![Type::Variable(rec)]),
+                    (TagName("Nil".into()), vec![]),
+                ],
+                TypeExtension::Closed,
+            )),
+        );
+        let typ = Type::Function(
+            vec![inner],
+            Box::new(Type::Variable(l2)),
+            Box::new(Type::Variable(a)),
+        );
+
+        let detail = typ.variables_detail();
+
+        assert_eq!(detail.lambda_set_variables, vec![l1, l2]);
+        assert_eq!(detail.type_variables, [a].into_iter().collect());
+        assert_eq!(detail.recursion_variables.into_vec(), vec![rec]);
+    }
+
+    #[test]
+    fn variables_detail_deeply_nested() {
+        const DEPTH: usize = 10_000;
+
+        let mut var_store = VarStore::default();
+        let var = var_store.fresh();
+
+        let mut typ = Type::Variable(var);
+        for _ in 0..DEPTH {
+            typ = Type::Apply(Symbol::LIST_LIST, vec![Loc::at_zero(typ)], Region::zero());
+        }
+
+        let detail = typ.variables_detail();
+        assert_eq!(detail.type_variables, [var].into_iter().collect());
+
+        // dropping the type recursively would overflow the stack as well
+        while let Type::Apply(_, mut args, _) = typ {
+            typ = args.pop().unwrap().value;
+        }
+    }
 }
@@ -18,11 +18,13 @@ use ven_pretty::DocAllocator;
 const SYNTAX_PROBLEM: &str = "SYNTAX PROBLEM";
 const NAMING_PROBLEM: &str = "NAMING PROBLEM";
 const UNRECOGNIZED_NAME: &str = "UNRECOGNIZED NAME";
+const USED_BEFORE_DEFINITION: &str = "USED BEFORE DEFINITION";
 const UNUSED_DEF: &str = "UNUSED DEFINITION";
 const UNUSED_IMPORT: &str = "UNUSED IMPORT";
 const UNUSED_ALIAS_PARAM: &str = "UNUSED TYPE ALIAS PARAMETER";
 const UNBOUND_TYPE_VARIABLE: &str = "UNBOUND TYPE VARIABLE";
 const UNUSED_ARG: &str = "UNUSED ARGUMENT";
+const DEPRECATED_VALUE: &str = "DEPRECATED VALUE";
 const MISSING_DEFINITION: &str = "MISSING DEFINITION";
 const UNKNOWN_GENERATES_WITH: &str = "UNKNOWN GENERATES FUNCTION";
 const DUPLICATE_FIELD_NAME: &str = "DUPLICATE FIELD NAME";
@@ -30,6 +32,7 @@ const DUPLICATE_TAG_NAME: &str = "DUPLICATE TAG NAME";
 const INVALID_UNICODE: &str = "INVALID UNICODE";
 pub const CIRCULAR_DEF: &str = "CIRCULAR DEFINITION";
 const DUPLICATE_NAME: &str = "DUPLICATE NAME";
+const SHADOWED_BUILTIN: &str = "SHADOWED BUILTIN";
 const VALUE_NOT_EXPOSED: &str = "NOT EXPOSED";
 const MODULE_NOT_IMPORTED: &str = "MODULE NOT IMPORTED";
 const NESTED_DATATYPE: &str = "NESTED DATATYPE";
@@ -230,6 +233,22 @@ pub fn can_problem<'b>(
 
             title = UNUSED_DEF.to_string();
         }
+        Problem::DeprecatedLookup {
+            symbol,
+            note,
+            region,
+        } => {
+            doc = alloc.stack([
+                alloc.concat([
+                    alloc.symbol_unqualified(symbol),
+                    alloc.reflow(" is deprecated:"),
+                ]),
+                alloc.region(lines.convert_region(region)),
+                alloc.string(note.into()),
+            ]);
+
+            title = DEPRECATED_VALUE.to_string();
+        }
         Problem::PrecedenceProblem(BothNonAssociative(region, left_bin_op, right_bin_op)) => {
             doc = alloc.stack([
                 if left_bin_op.value == right_bin_op.value {
@@ -1505,6 +1524,39 @@ fn report_shadowing<'b>(
     kind: ShadowKind,
 ) -> (&'static str, RocDocBuilder<'b>) {
     let (what, what_plural, is_builtin) = match kind {
+        ShadowKind::Builtin(builtin) => {
+            let shadows = alloc.concat([
+                alloc.reflow("This definition shadows the builtin "),
+                alloc.symbol_qualified(builtin),
+                alloc.reflow(":"),
+            ]);
+
+            // builtins that are imported by default have no import to point at
+            let doc = if original_region == Region::zero() {
+                alloc.stack([
+                    shadows,
+                    alloc.region(lines.convert_region(shadow.region)),
+                    alloc.concat([
+                        alloc.ident(shadow.value),
+                        alloc.reflow(" is in scope by default, so it's easy to use the wrong one by accident. Give this definition a different name."),
+                    ]),
+                ])
+            } else {
+                alloc.stack([
+                    shadows,
+                    alloc.region(lines.convert_region(shadow.region)),
+                    alloc.reflow("It was imported here:"),
+                    alloc.region(lines.convert_region(original_region)),
+                    alloc.concat([
+                        alloc.reflow("Give this definition a different name, or remove "),
+                        alloc.ident(shadow.value),
+                        alloc.reflow(" from the import if you don't need the builtin."),
+                    ]),
+                ])
+            };
+
+            return (SHADOWED_BUILTIN, doc);
+        }
         ShadowKind::Variable => ("variable", "variables", false),
         ShadowKind::Alias(sym) => ("alias", "aliases", sym.is_builtin()),
         ShadowKind::Opaque(sym) => ("opaque type", "opaque types", sym.is_builtin()),
@@ -1579,6 +1631,61 @@ fn pretty_runtime_error<'b>(
             doc = not_found(alloc, lines, loc_name.region, &loc_name.value, options);
             title = UNRECOGNIZED_NAME;
         }
+        RuntimeError::LookupNotInScopeButExposed { usage, exposed_by } => {
+            let qualified = |module_name: &ModuleName| {
+                alloc
+                    .text(format!("{}.{}", module_name, usage.value))
+                    .annotate(Annotation::Symbol)
+            };
+
+            doc = alloc.stack([
+                alloc.concat([
+                    alloc.reflow("Nothing is named `"),
+                    alloc.string(usage.value.to_string()),
+                    alloc.reflow("` in this scope."),
+                ]),
+                alloc.region(lines.convert_region(usage.region)),
+                alloc.reflow(
+                    "But imported modules expose a value with this name, so maybe you meant:",
+                ),
+                alloc.vcat(exposed_by.iter().map(qualified)).indent(4),
+            ]);
+            title = UNRECOGNIZED_NAME;
+        }
+        RuntimeError::LookupNotInScopeButLooksAlike { usage, similar } => {
+            doc = alloc.stack([
+                alloc.concat([
+                    alloc.reflow("Nothing is named `"),
+                    alloc.string(usage.value.to_string()),
+                    alloc.reflow("` in this scope."),
+                ]),
+                alloc.region(lines.convert_region(usage.region)),
+                alloc.concat([
+                    alloc.reflow("There is a "),
+                    alloc.ident(similar),
+                    alloc.reflow(" value that looks the same, but is written with different unicode characters."),
+                ]),
+                alloc.reflow("Try typing the name again, or copying it from its definition."),
+            ]);
+            title = UNRECOGNIZED_NAME;
+        }
+        RuntimeError::UsedBeforeDefinition {
+            usage,
+            definition_region,
+        } => {
+            doc = alloc.stack([
+                alloc.concat([
+                    alloc.reflow("The "),
+                    alloc.ident(usage.value),
+                    alloc.reflow(" value is used here before it is defined:"),
+                ]),
+                alloc.region(lines.convert_region(usage.region)),
+                alloc.reflow("It is only defined further down, here:"),
+                alloc.region(lines.convert_region(definition_region)),
+                alloc.reflow("Try moving the definition above its first use."),
+            ]);
+            title = USED_BEFORE_DEFINITION;
+        }
         RuntimeError::CircularDef(entries) => {
             doc = to_circular_def_doc(alloc, lines, &entries);
             title = CIRCULAR_DEF;
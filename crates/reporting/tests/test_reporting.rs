@@ -579,6 +579,56 @@ mod test_reporting {
     "###
     );
 
+    test_report!(
+        report_shadowing_imported_builtin,
+        indoc!(
+            r#"
+            app "test" imports [List.{ map }] provides [main] to "./platform"
+
+            main = \map -> map
+            "#
+        ),
+        @r###"
+    ── SHADOWED BUILTIN ────────────────────────────────────── /code/proj/Main.roc ─
+
+    This definition shadows the builtin `List.map`:
+
+    3│  main = \map -> map
+                ^^^
+
+    It was imported here:
+
+    1│  app "test" imports [List.{ map }] provides [main] to "./platform"
+                                   ^^^
+
+    Give this definition a different name, or remove `map` from the import
+    if you don't need the builtin.
+    "###
+    );
+
+    test_report!(
+        report_shadowing_default_builtin,
+        indoc!(
+            r#"
+            x : [Yes, No] as Str
+            x = Yes
+
+            x
+            "#
+        ),
+        @r###"
+    ── SHADOWED BUILTIN ────────────────────────────────────── /code/proj/Main.roc ─
+
+    This definition shadows the builtin `Str.Str`:
+
+    4│      x : [Yes, No] as Str
+                             ^^^
+
+    `Str` is in scope by default, so it's easy to use the wrong one by
+    accident. Give this definition a different name.
+    "###
+    );
+
     test_report!(
         report_precedence_problem_single_line,
         indoc!(
@@ -654,12 +704,67 @@ mod test_reporting {
     4│      if true then 1 else 2
                ^^^^
 
-    Did you mean one of these?
+    But imported modules expose a value with this name, so maybe you
+    meant:
 
-        Str
-        Frac
-        Num
-        Err
+        `Bool.true`
+    "###
+    );
+
+    test_report!(
+        unrecognized_name_exposed_by_import,
+        indoc!(
+            r#"
+            startsWith "abc" "a"
+            "#
+        ),
+        @r###"
+    ── UNRECOGNIZED NAME ───────────────────────────────────── /code/proj/Main.roc ─
+
+    Nothing is named `startsWith` in this scope.
+
+    4│      startsWith "abc" "a"
+            ^^^^^^^^^^
+
+    But imported modules expose a value with this name, so maybe you
+    meant:
+
+        `Str.startsWith`
+        `List.startsWith`
+    "###
+    );
+
+    test_report!(
+        unrecognized_name_looks_alike,
+        indoc!(
+            r#"
+            file = "notes.txt"
+
+            ｆｉｌｅ
+            "#
+        ),
+        @r###"
+    ── UNRECOGNIZED NAME ───────────────────────────────────── /code/proj/Main.roc ─
+
+    Nothing is named `ｆｉｌｅ` in this scope.
+
+    6│      ｆｉｌｅ
+            ^^^^^^^^^^^^
+
+    There is a `file` value that looks the same, but is written with
+    different unicode characters.
+
+    Try typing the name again, or copying it from its definition.
+
+    ── UNUSED DEFINITION ───────────────────────────────────── /code/proj/Main.roc ─
+
+    `file` is not used anywhere in your code.
+
+    4│      file = "notes.txt"
+            ^^^^
+
+    If you didn't intend on using `file` then remove it so future readers of
+    your code don't wonder why it is there.
     "###
     );
 
@@ -0,0 +1,88 @@
+use core::ffi::c_void;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use roc_std::{RocBox, RocBoxArena};
+
+const NODES: usize = 100_000;
+
+#[no_mangle]
+pub unsafe extern "C" fn roc_alloc(size: usize, _alignment: u32) -> *mut c_void {
+    libc::malloc(size)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn roc_realloc(
+    c_ptr: *mut c_void,
+    new_size: usize,
+    _old_size: usize,
+    _alignment: u32,
+) -> *mut c_void {
+    libc::realloc(c_ptr, new_size)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn roc_dealloc(c_ptr: *mut c_void, _alignment: u32) {
+    libc::free(c_ptr)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn roc_panic(_c_ptr: *mut c_void, _tag_id: u32) {
+    panic!("roc_panic during benchmark")
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn roc_memcpy(dst: *mut c_void, src: *mut c_void, n: usize) -> *mut c_void {
+    libc::memcpy(dst, src, n)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn roc_memset(dst: *mut c_void, c: i32, n: usize) -> *mut c_void {
+    libc::memset(dst, c, n)
+}
+
+/// A node of a binary tree, which refers to its parent by index
+struct Node {
+    value: u64,
+    parent: usize,
+}
+
+/// Box the `NODES` nodes of a tree, in breadth-first order
+fn build_tree(mut new_box: impl FnMut(Node) -> RocBox<Node>) -> Vec<RocBox<Node>> {
+    (0..NODES)
+        .map(|index| {
+            new_box(Node {
+                value: index as u64,
+                parent: index.saturating_sub(1) / 2,
+            })
+        })
+        .collect()
+}
+
+pub fn construction_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build a tree of 100k boxed nodes");
+
+    group.bench_function("RocBox::new", |b| {
+        b.iter(|| {
+            let nodes = build_tree(RocBox::new);
+            let last = &nodes[NODES - 1];
+
+            black_box((last.value, last.parent))
+        })
+    });
+
+    group.bench_function("RocBoxArena::alloc", |b| {
+        b.iter(|| {
+            let arena = RocBoxArena::with_capacity(NODES);
+            let nodes = build_tree(|node| unsafe { arena.alloc(node) });
+            let last = &nodes[NODES - 1];
+
+            black_box((last.value, last.parent))
+
+            // the nodes are dropped before the arena
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, construction_benchmark);
+criterion_main!(benches);
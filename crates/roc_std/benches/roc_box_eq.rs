@@ -0,0 +1,57 @@
+use core::ffi::c_void;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use roc_std::RocBox;
+
+#[no_mangle]
+pub unsafe extern "C" fn roc_alloc(size: usize, _alignment: u32) -> *mut c_void {
+    libc::malloc(size)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn roc_realloc(
+    c_ptr: *mut c_void,
+    new_size: usize,
+    _old_size: usize,
+    _alignment: u32,
+) -> *mut c_void {
+    libc::realloc(c_ptr, new_size)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn roc_dealloc(c_ptr: *mut c_void, _alignment: u32) {
+    libc::free(c_ptr)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn roc_panic(_c_ptr: *mut c_void, _tag_id: u32) {
+    panic!("roc_panic during benchmark")
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn roc_memcpy(dst: *mut c_void, src: *mut c_void, n: usize) -> *mut c_void {
+    libc::memcpy(dst, src, n)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn roc_memset(dst: *mut c_void, c: i32, n: usize) -> *mut c_void {
+    libc::memset(dst, c, n)
+}
+
+/// Large enough that comparing the contents is noticeably slower than comparing two pointers
+type Large = [u64; 4096];
+
+fn eq_benchmark(c: &mut Criterion) {
+    let roc_box: RocBox<Large> = RocBox::new([7; 4096]);
+    let clone = roc_box.clone();
+
+    c.bench_function("ptr_eq_or_eq of clones (shared allocation)", |b| {
+        b.iter(|| RocBox::ptr_eq_or_eq(black_box(&roc_box), black_box(&clone)))
+    });
+
+    c.bench_function("eq of clones (compares the contents)", |b| {
+        b.iter(|| black_box(&roc_box) == black_box(&clone))
+    });
+}
+
+criterion_group!(benches, eq_benchmark);
+criterion_main!(benches);
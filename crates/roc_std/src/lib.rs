@@ -2,6 +2,8 @@
 #![no_std]
 #![crate_type = "lib"]
 
+extern crate alloc;
+
 use arrayvec::ArrayString;
 use core::cmp::Ordering;
 use core::ffi::c_void;
@@ -18,12 +20,14 @@ mod roc_set;
 mod roc_str;
 mod storage;
 
-pub use roc_box::{RocBox, SendSafeRocBox};
+pub use roc_box::{
+    RocBox, RocBoxArena, RocBoxMutGuard, RocBoxRef, SendSafeRocBox, TaggedRocBox, ZeroizingRocBox,
+};
 pub use roc_dict::RocDict;
 pub use roc_list::{RocList, SendSafeRocList};
 pub use roc_set::RocSet;
 pub use roc_str::{InteriorNulError, RocStr, SendSafeRocStr};
-pub use storage::Storage;
+pub use storage::{Refcount, Storage};
 
 // A list of C functions that are being imported
 extern "C" {
@@ -40,6 +44,41 @@ extern "C" {
     pub fn roc_memset(dst: *mut c_void, c: i32, n: usize) -> *mut c_void;
 }
 
+/// `roc_alloc` returned null for an allocation of this size and alignment, see
+/// [`RocBox::try_new`](crate::RocBox::try_new)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllocError {
+    pub size: usize,
+    pub alignment: u32,
+}
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Allocation of {} bytes with alignment {} failed",
+            self.size, self.alignment
+        )
+    }
+}
+
+/// Report that `roc_alloc` or `roc_realloc` returned null, through `roc_panic` like the other
+/// runtime errors of a Roc program. The message is passed as a nul-terminated string.
+#[cold]
+#[inline(never)]
+pub(crate) fn allocation_failed(size: usize, alignment: u32) -> ! {
+    use core::fmt::Write;
+
+    // allocating the message could fail too, so it lives on the stack
+    let mut message = ArrayString::<96>::new();
+    let _ = write!(message, "{}\0", AllocError { size, alignment });
+
+    unsafe { roc_panic(message.as_ptr() as *mut c_void, 0) };
+
+    // roc_panic is not supposed to return
+    panic!("{}", message.trim_end_matches('\0'))
+}
+
 pub fn roc_alloc_refcounted<T>() -> *mut T {
     let size = core::mem::size_of::<T>();
     let align = core::mem::align_of::<T>();
@@ -54,6 +93,11 @@ fn roc_alloc_refcounted_help(mut size: usize, mut align: usize) -> *mut u8 {
 
     unsafe {
         let allocation_ptr = roc_alloc(size, align as _) as *mut u8;
+
+        if allocation_ptr.is_null() {
+            allocation_failed(size, align as _);
+        }
+
         let data_ptr = allocation_ptr.add(prefix);
         let storage_ptr = (data_ptr as *mut crate::Storage).sub(1);
 
@@ -63,6 +107,74 @@ fn roc_alloc_refcounted_help(mut size: usize, mut align: usize) -> *mut u8 {
     }
 }
 
+/// Reference counting the way Roc code does it, for values that are passed back and forth between
+/// Roc and the host.
+///
+/// Roc copies values bit for bit, and then calls `inc` on the copy, which increments the
+/// reference counts of the allocations it refers to. `dec` gives up a reference, and releases
+/// the allocation, and recursively the values in it, once it was the last one. For the types in
+/// this crate, `Clone` and `Drop` follow the same conventions.
+///
+/// # Safety
+///
+/// After `inc`, a bit for bit copy of the value must be safe to use and drop independently of
+/// the original. Types that are not reference counted, and do nothing in `inc` and `dec`,
+/// therefore must not have drop glue either, like the primitives this is implemented for.
+pub unsafe trait RocRefcounted {
+    /// Account for a new reference, e.g. a bit for bit copy of the value.
+    fn inc(&mut self);
+
+    /// Give up this reference, releasing the value if it was the last one.
+    ///
+    /// # Safety
+    ///
+    /// The value must not be used or dropped afterwards, e.g. it can be forgotten.
+    unsafe fn dec(&mut self);
+
+    /// Whether values of this type refer to reference counted allocations at all
+    fn is_refcounted() -> bool;
+}
+
+macro_rules! roc_refcounted_noop_impl {
+    ( $( $T:ty ),+ ) => {
+        $(
+            unsafe impl RocRefcounted for $T {
+                fn inc(&mut self) {}
+
+                unsafe fn dec(&mut self) {}
+
+                fn is_refcounted() -> bool {
+                    false
+                }
+            }
+        )+
+    };
+}
+
+roc_refcounted_noop_impl!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    RocDec,
+    RocOrder,
+    I128,
+    U128
+);
+
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum RocOrder {
@@ -125,6 +237,27 @@ where
     }
 }
 
+unsafe impl<T, E> RocRefcounted for RocResult<T, E>
+where
+    T: RocRefcounted,
+    E: RocRefcounted,
+{
+    fn inc(&mut self) {
+        match self.tag {
+            RocResultTag::RocOk => unsafe { (*self.payload.ok).inc() },
+            RocResultTag::RocErr => unsafe { (*self.payload.err).inc() },
+        }
+    }
+
+    unsafe fn dec(&mut self) {
+        unsafe { core::ptr::drop_in_place(self) }
+    }
+
+    fn is_refcounted() -> bool {
+        T::is_refcounted() || E::is_refcounted()
+    }
+}
+
 impl<T, E> RocResult<T, E> {
     pub fn ok(payload: T) -> Self {
         Self {
@@ -1,47 +1,330 @@
 #![deny(unsafe_op_in_unsafe_fn)]
 
-use crate::{roc_alloc, roc_dealloc, storage::Storage};
+use crate::{
+    allocation_failed, roc_alloc, roc_dealloc,
+    storage::{Refcount, Storage},
+    AllocError, RocRefcounted,
+};
+use alloc::{boxed::Box, vec::Vec};
 use core::{
+    alloc::Layout,
+    borrow::Borrow,
     cell::Cell,
     cmp::{self, Ordering},
-    fmt::Debug,
-    mem,
-    ops::Deref,
+    fmt::{self, Debug},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem::{self, ManuallyDrop, MaybeUninit},
+    ops::{Deref, DerefMut},
+    pin::Pin,
     ptr::{self, NonNull},
+    sync::atomic::{compiler_fence, Ordering as AtomicOrdering},
 };
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The largest alignment that `roc_alloc` is relied on to honor. Hosts commonly use an
+/// allocator like `malloc`, which does not guarantee more. Boxes of contents with a larger
+/// alignment over-allocate, and align the contents themselves.
+const MAX_GUARANTEED_ALIGNMENT: usize = 16;
+
+/// A reference-counted box, laid out like a Roc `Box`: a pointer to the contents, with the
+/// reference count right in front of them.
+///
+/// Like `Rc<T>`, a box owns its contents, and is covariant in `T`: the contents are only
+/// mutable through a unique box, see [`RocBox::get_mut`], so a `RocBox<&'static str>` can be
+/// used as a `RocBox<&'a str>`.
+///
+/// Without the `atomic-refcount` feature, the reference count is updated without
+/// synchronization, so boxes can't be sent or shared between threads (see [SendSafeRocBox]):
+///
+#[cfg_attr(not(feature = "atomic-refcount"), doc = "```compile_fail")]
+#[cfg_attr(feature = "atomic-refcount", doc = "```no_run")]
+/// fn assert_send<T: Send>() {}
+/// assert_send::<roc_std::RocBox<u64>>();
+/// ```
+///
+#[cfg_attr(not(feature = "atomic-refcount"), doc = "```compile_fail")]
+#[cfg_attr(feature = "atomic-refcount", doc = "```no_run")]
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<roc_std::RocBox<u64>>();
+/// ```
+///
+/// With it, boxes of contents that are `Send` and `Sync` are both, like an `Arc`:
+///
+/// ```compile_fail
+/// fn assert_send<T: Send>() {}
+/// assert_send::<roc_std::RocBox<core::cell::Cell<u64>>>();
+/// ```
 #[repr(C)]
 pub struct RocBox<T> {
     contents: NonNull<T>,
+    /// Tells drop check that a box owns a `T`, which it may drop
+    _marker: PhantomData<T>,
 }
 
 impl<T> RocBox<T> {
     pub fn new(contents: T) -> Self {
-        let alignment = Self::alloc_alignment();
-        let bytes = mem::size_of::<T>() + alignment;
+        match Self::try_new(contents) {
+            Ok(roc_box) => roc_box,
+            Err(error) => allocation_failed(error.size, error.alignment),
+        }
+    }
 
-        let ptr = unsafe { roc_alloc(bytes, alignment as u32) };
+    /// Like [`RocBox::new`], but when `roc_alloc` returns null, this returns an error instead of
+    /// calling `roc_panic`, e.g. for hosts with little memory that can recover from that.
+    ///
+    /// On error, `contents` are dropped before this returns, and nothing stays allocated.
+    pub fn try_new(contents: T) -> Result<Self, AllocError> {
+        let contents_ptr = Self::try_allocate(0)?;
 
-        if ptr.is_null() {
-            todo!("Call roc_panic with the info that an allocation failed.");
+        // The memory is uninitialized, so there is no previous value that must be dropped
+        unsafe { ptr::write(contents_ptr.as_ptr(), contents) };
+
+        Ok(Self {
+            contents: contents_ptr,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Like [`RocBox::new`], but the contents are created by `f`, which gets the address they
+    /// will be stored at. This lets values refer to their own address.
+    ///
+    /// The contents are not initialized yet when `f` runs, so `f` must not read from or write
+    /// to the pointer. If `f` panics, the allocation is leaked.
+    pub fn new_with<F>(f: F) -> Self
+    where
+        F: FnOnce(NonNull<T>) -> T,
+    {
+        let contents_ptr = Self::allocate(0);
+
+        let contents = f(contents_ptr);
+        unsafe { ptr::write(contents_ptr.as_ptr(), contents) };
+
+        Self {
+            contents: contents_ptr,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Like [`RocBox::new`], but `make` creates the contents after the box has been allocated.
+    /// For contents that allocate themselves, like a [`RocList`](crate::RocList), this
+    /// leaves room to place their allocation next to the box's in the future, without changing
+    /// the signature. For now it is the same as `RocBox::new(make())`, except that the box's
+    /// allocation is leaked if `make` panics.
+    pub fn new_from_fn<F>(make: F) -> Self
+    where
+        F: FnOnce() -> T,
+    {
+        Self::new_with(|_| make())
+    }
+
+    /// A box with room for the contents, whose reference count is initialized, but whose
+    /// contents are not, e.g. to let C code or Roc fill them in through [`RocBox::as_ptr`]. See
+    /// [`RocBox::write`] and [`RocBox::assume_init`].
+    ///
+    /// Dropping the box before it is initialized frees the memory without dropping a `T`.
+    pub fn new_uninit() -> RocBox<MaybeUninit<T>> {
+        // Allocated as a box of `T`, so that zero-sized contents with drop glue get an
+        // allocation here too, like they would with `RocBox::new`.
+        let contents = Self::allocate(0);
+
+        RocBox {
+            contents: contents.cast(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Like [`RocBox::new`], but a 16-bit tag is stored in the same allocation, after the
+    /// contents. The layout of the box itself, and of untagged boxes, is not affected.
+    pub fn new_tagged(contents: T, tag: u16) -> TaggedRocBox<T> {
+        let contents_ptr =
+            Self::allocate(Self::tag_offset() - mem::size_of::<T>() + Self::TAG_SIZE);
+
+        unsafe {
+            ptr::write(contents_ptr.as_ptr(), contents);
+            ptr::write(Self::tag_ptr(contents_ptr), tag);
+        }
+
+        TaggedRocBox(Self {
+            contents: contents_ptr,
+            _marker: PhantomData,
+        })
+    }
+
+    const TAG_SIZE: usize = mem::size_of::<u16>();
+
+    /// The tag of a tagged box is stored right after the contents, 2-byte aligned.
+    #[inline(always)]
+    fn tag_offset() -> usize {
+        let size = mem::size_of::<T>();
+
+        size + size % Self::TAG_SIZE
+    }
+
+    fn tag_ptr(contents: NonNull<T>) -> *mut u16 {
+        unsafe {
+            contents
+                .as_ptr()
+                .cast::<u8>()
+                .add(Self::tag_offset())
+                .cast()
         }
+    }
 
-        // Initialize the reference count.
-        let refcount_one = Storage::new_reference_counted();
-        unsafe { ptr.cast::<Storage>().write(refcount_one) };
+    /// Allocate room for the storage, the contents, and `extra` bytes after the contents, and
+    /// initialize the storage with a reference count of one. Returns a pointer to the
+    /// (uninitialized) contents.
+    ///
+    /// Like `Box`, nothing is allocated for zero-sized contents without drop glue: nothing needs
+    /// to happen when the last reference to them is dropped, so they need no reference count.
+    /// The returned pointer is then dangling, see [`RocBox::is_dangling`].
+    fn allocate(extra: usize) -> NonNull<T> {
+        match Self::try_allocate(extra) {
+            Ok(contents_ptr) => contents_ptr,
+            Err(error) => allocation_failed(error.size, error.alignment),
+        }
+    }
+
+    /// Like [`RocBox::allocate`], but returns an error if `roc_alloc` returns null.
+    fn try_allocate(extra: usize) -> Result<NonNull<T>, AllocError> {
+        if extra == 0 && Self::is_unallocated() {
+            return Ok(NonNull::dangling());
+        }
+
+        let layout = Self::allocation_layout(extra);
+        let alignment = layout.align();
+
+        let ptr = unsafe { roc_alloc(layout.size(), alignment as u32) };
+
+        if ptr.is_null() {
+            return Err(AllocError {
+                size: layout.size(),
+                alignment: alignment as u32,
+            });
+        }
 
-        let contents = unsafe {
-            let contents_ptr = ptr.cast::<u8>().add(alignment).cast::<T>();
+        unsafe {
+            let contents_ptr = Self::place_contents(ptr.cast());
 
-            *contents_ptr = contents;
+            // Initialize the reference count.
+            let refcount_one = Storage::new_reference_counted();
+            Self::storage_ptr(contents_ptr).write(refcount_one);
 
             // We already verified that the original alloc pointer was non-null,
-            // and this one is the alloc pointer with `alignment` bytes added to it,
+            // and this one is the alloc pointer with the header size added to it,
             // so it should be non-null too.
-            NonNull::new_unchecked(contents_ptr)
-        };
+            Ok(NonNull::new_unchecked(contents_ptr))
+        }
+    }
+
+    /// The contents pointer for a new allocation, which is `header_size` bytes in.
+    ///
+    /// Over-aligned contents are moved further in to align them, by at most
+    /// [`RocBox::alignment_padding`] bytes. The offset is then stored in the header, right in
+    /// front of the storage, so that [`RocBox::allocation_start`] can find the allocation again.
+    ///
+    /// # Safety
+    ///
+    /// `allocation` must point to a new allocation with the size of [`RocBox::allocation_layout`].
+    unsafe fn place_contents(allocation: *mut u8) -> *mut T {
+        let header_size = Self::header_size();
+
+        if Self::alignment_padding() == 0 {
+            return unsafe { allocation.add(header_size).cast() };
+        }
+
+        let alignment = Self::alloc_alignment();
+        let unaligned = allocation as usize + header_size;
+        let offset = header_size + (unaligned.wrapping_neg() & (alignment - 1));
+
+        unsafe {
+            let contents = allocation.add(offset);
+
+            // the header of over-aligned contents has room for the storage and the offset
+            contents.cast::<usize>().sub(2).write(offset);
+
+            contents.cast()
+        }
+    }
+
+    /// The start of the allocation that `contents` points into, see [`RocBox::place_contents`]
+    ///
+    /// # Safety
+    ///
+    /// `contents` must point to the contents of a box allocated by [`RocBox::allocate`].
+    unsafe fn allocation_start(contents: *mut T) -> *mut u8 {
+        let contents = contents.cast::<u8>();
+
+        unsafe {
+            if Self::alignment_padding() == 0 {
+                contents.sub(Self::header_size())
+            } else {
+                contents.sub(contents.cast::<usize>().sub(2).read())
+            }
+        }
+    }
+
+    /// Like [`RocBox::new`], but the contents are pinned, e.g. for values that point into
+    /// themselves. Clones of the returned box are pinned too, and share the same contents.
+    ///
+    /// The contents of a box stay at the same address for as long as the allocation lives:
+    /// moving or cloning a box only copies the pointer to them. The APIs that move contents out
+    /// of an allocation, like [`RocBox::try_unwrap`], [`RocBox::into_inner`], or the
+    /// copy-on-write of [`RocBox::make_mut`], take the box by value or by `&mut`, which a `Pin`
+    /// only hands out for `Unpin` contents, like `Pin<Box<T>>`.
+    ///
+    /// Unlike `Box`, there is no conversion from an existing `RocBox` into a pinned one: another
+    /// reference to the contents could still move them out once the pinned one is dropped.
+    pub fn pin(contents: T) -> Pin<Self> {
+        // The contents are only reachable through the returned box and its clones.
+        unsafe { Pin::new_unchecked(Self::new(contents)) }
+    }
+
+    /// Like [`RocBox::new`], but the contents are overwritten with zeros when the last reference
+    /// is dropped, before the memory is released. Meant for secrets like keys or passwords.
+    pub fn new_zeroizing(contents: T) -> ZeroizingRocBox<T> {
+        ZeroizingRocBox(ManuallyDrop::new(Self::new(contents)))
+    }
+
+    /// The layout of the allocation of a box made by [`RocBox::new`]: the storage header, padded
+    /// to the alignment of the contents (see [`Storage::header_size`]), followed by the
+    /// contents. This is the size and
+    /// alignment that are passed to `roc_alloc`, e.g. for a host to check the allocation it is
+    /// about to free in `roc_dealloc`.
+    ///
+    /// Zero-sized contents without drop glue are not allocated at all.
+    pub fn contents_layout() -> Layout {
+        Self::allocation_layout(0)
+    }
+
+    /// The number of bytes a box made by [`RocBox::new`] occupies, storage header and alignment
+    /// padding included, e.g. for a memory profiler to add up. This is the size of
+    /// [`RocBox::contents_layout`], which is passed to `roc_alloc`.
+    ///
+    /// Zero-sized contents without drop glue are not allocated, so they take up no bytes.
+    pub fn total_size() -> usize {
+        if Self::is_unallocated() {
+            return 0;
+        }
 
-        Self { contents }
+        Self::allocation_layout(0).size()
+    }
+
+    /// Whether boxes of `T` are not allocated, see [`RocBox::allocate`]
+    #[inline(always)]
+    fn is_unallocated() -> bool {
+        mem::size_of::<T>() == 0 && !mem::needs_drop::<T>()
+    }
+
+    fn allocation_layout(extra: usize) -> Layout {
+        let bytes = Self::header_size() + Self::alignment_padding() + mem::size_of::<T>() + extra;
+
+        match Layout::from_size_align(bytes, Self::alloc_alignment()) {
+            Ok(layout) => layout,
+            Err(_) => panic!("RocBox contents are too large"),
+        }
     }
 
     #[inline(always)]
@@ -49,31 +332,595 @@ impl<T> RocBox<T> {
         mem::align_of::<T>().max(mem::align_of::<Storage>())
     }
 
-    pub fn into_inner(self) -> T {
-        unsafe { ptr::read(self.contents.as_ptr() as *mut T) }
+    /// The offset of the contents from the start of the allocation, see [`Storage::header_size`]
+    #[inline(always)]
+    fn header_size() -> usize {
+        Storage::header_size(mem::align_of::<T>())
     }
 
-    fn storage(&self) -> &Cell<Storage> {
-        let alignment = Self::alloc_alignment();
+    /// The extra bytes that are allocated to align contents that are aligned to more than
+    /// [`MAX_GUARANTEED_ALIGNMENT`]
+    #[inline(always)]
+    fn alignment_padding() -> usize {
+        Self::alloc_alignment().saturating_sub(MAX_GUARANTEED_ALIGNMENT)
+    }
+
+    /// The storage of the allocation that `contents` points into, which is right before them
+    #[inline(always)]
+    fn storage_ptr(contents: *mut T) -> *mut Storage {
+        contents.cast::<Storage>().wrapping_sub(1)
+    }
+
+    /// Reinterpret this box as a `RocBox<U>`, handing over the allocation
+    /// without touching the reference count.
+    ///
+    /// # Safety
+    ///
+    /// `T` and `U` must have identical layout, for example because one is a
+    /// `#[repr(transparent)]` wrapper around the other. In particular their
+    /// alignments must match, because the offset of the storage in front of
+    /// the contents is derived from the alignment.
+    pub unsafe fn cast<U>(self) -> RocBox<U> {
+        debug_assert_eq!(mem::size_of::<T>(), mem::size_of::<U>());
+        debug_assert_eq!(mem::align_of::<T>(), mem::align_of::<U>());
+
+        let contents = self.contents.cast::<U>();
+
+        // The allocation now belongs to the returned box.
+        mem::forget(self);
+
+        RocBox {
+            contents,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Give up ownership of the box, e.g. to pass it through a C API, without touching the
+    /// reference count. The returned pointer points to the contents, like the pointers Roc
+    /// itself passes boxes as.
+    ///
+    /// The reference this box held now belongs to the caller: it is leaked unless the pointer is
+    /// turned back into a box with [`RocBox::from_raw`], exactly once, which releases it again
+    /// when that box is dropped.
+    pub fn into_raw(self) -> *mut T {
+        let contents = self.contents.as_ptr();
+
+        mem::forget(self);
+
+        contents
+    }
+
+    /// Take ownership of a box that was given up by [`RocBox::into_raw`] or passed by Roc.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to the contents of a box of the same `T` that was allocated with
+    /// `roc_alloc`, with the reference count in front of it. Contents that are aligned to more
+    /// than 16 bytes must have been allocated by `RocBox`, which records where their
+    /// allocation starts. Every pointer returned by
+    /// [`RocBox::into_raw`] must be passed to `from_raw` exactly once, since the reference it
+    /// represents is released when the returned box is dropped.
+    pub unsafe fn from_raw(ptr: *mut T) -> Self {
+        debug_assert!(!ptr.is_null());
+
+        Self {
+            contents: unsafe { NonNull::new_unchecked(ptr) },
+            _marker: PhantomData,
+        }
+    }
+
+    /// A box for the contents at `ptr`, e.g. a pointer that C code got from [`RocBox::as_ptr`].
+    ///
+    /// With `increment`, the returned box is a new reference, and the reference count is
+    /// incremented, like cloning. Without it, the caller hands over a reference it owns, like
+    /// [`RocBox::from_raw`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point exactly at the contents of a live box of the same `T` that was allocated
+    /// by `RocBox::new` (or by Roc): the reference count is found at a fixed offset in front of
+    /// the contents, so a pointer into the middle of the contents, or to a value that is not in a
+    /// box at all, is undefined behavior. Without `increment`, the caller must own a reference
+    /// that is not released in any other way.
+    pub unsafe fn from_contents_ptr(ptr: NonNull<T>, increment: bool) -> Self {
+        let roc_box = ManuallyDrop::new(Self {
+            contents: ptr,
+            _marker: PhantomData,
+        });
+
+        if increment {
+            RocBox::clone(&roc_box)
+        } else {
+            ManuallyDrop::into_inner(roc_box)
+        }
+    }
+
+    /// Whether the two boxes share the same allocation, like `Rc::ptr_eq`.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.contents == other.contents
+    }
+
+    /// Equality that skips comparing the contents when both boxes share an allocation. This is
+    /// only sound for a reflexive equality, hence `T: Eq`; `PartialEq` always compares the
+    /// contents, so that e.g. a box holding `f64::NAN` is not equal to its clone.
+    pub fn ptr_eq_or_eq(this: &Self, other: &Self) -> bool
+    where
+        T: Eq,
+    {
+        Self::ptr_eq(this, other) || this.deref() == other.deref()
+    }
+
+    /// Whether this is the only reference to the contents. Readonly boxes, which may be
+    /// referenced from anywhere, are never unique.
+    pub fn is_unique(&self) -> bool {
+        self.load_storage()
+            .map_or(false, |storage| storage.is_unique())
+    }
+
+    /// Debug output with the storage metadata next to the contents: the reference count, whether
+    /// the box is readonly, and the address of the contents. Useful to track down leaks and
+    /// premature frees, which the regular `Debug` output, that of the contents, can't show.
+    pub fn debug_full(&self) -> impl Debug + '_
+    where
+        T: Debug,
+    {
+        FullDebug(self)
+    }
+
+    /// The number of references to the contents, this one included. Readonly boxes are not
+    /// reference counted, so for them this is `None`.
+    pub fn strong_count(&self) -> Option<usize> {
+        self.load_storage()
+            .and_then(|storage| storage.reference_count())
+    }
+
+    /// The reference count of the contents, e.g. to debug a host that leaks or frees boxes
+    /// early. This only reads the storage. Like readonly boxes, boxes of zero-sized types
+    /// without an allocation report [`Refcount::Readonly`].
+    pub fn refcount(&self) -> Refcount {
+        self.load_storage()
+            .map_or(Refcount::Readonly, |storage| storage.refcount())
+    }
+
+    /// Boxes of zero-sized types without an allocation are readonly too.
+    pub fn is_readonly(&self) -> bool {
+        self.load_storage()
+            .map_or(true, |storage| storage.is_readonly())
+    }
+
+    /// Convert to a [SendSafeRocBox] without copying. Unlike the `From` conversion, which
+    /// deep-copies a shared box, this returns the box back as `Err` when it is shared, so that
+    /// accidental sharing across threads is noticed where it happens.
+    pub fn try_send_safe(self) -> Result<SendSafeRocBox<T>, RocBox<T>> {
+        if self.is_unique() || self.is_readonly() {
+            Ok(SendSafeRocBox(self))
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Convert every box with the `From` conversion. This is only a shorthand for mapping
+    /// [`SendSafeRocBox::from`] over `boxes`, so boxes that share an allocation are each
+    /// deep-copied into their own: every element can be sent to a different thread, and a shared
+    /// copy would share a reference count that is not updated atomically.
+    pub fn into_send_safe_vec(boxes: Vec<RocBox<T>>) -> Vec<SendSafeRocBox<T>>
+    where
+        T: RocRefcounted,
+    {
+        boxes.into_iter().map(SendSafeRocBox::from).collect()
+    }
+
+    /// Marks a box as readonly. This means that it will be leaked.
+    /// For constants passed in from platform to application, this may be reasonable.
+    ///
+    /// # Safety
+    ///
+    /// See [`RocList::set_readonly`](crate::RocList::set_readonly): only values that should
+    /// live for the entire application run should be marked read-only.
+    pub unsafe fn set_readonly(&self) {
+        self.update_storage(|storage| *storage = Storage::Readonly);
+    }
+
+    /// Freeze the box, so that it can be shared for free: cloning a readonly box does not touch
+    /// its reference count, and it converts into a [SendSafeRocBox] without copying.
+    ///
+    /// The contents are leaked on purpose. Neither this box nor any of its clones, including
+    /// those that already exist, ever drop them or deallocate their memory.
+    pub fn into_readonly(self) -> Self {
+        // Leaking is safe, and this box is the one that should live forever.
+        unsafe { self.set_readonly() };
+
+        self
+    }
+
+    /// Make the box readonly, like [`RocBox::into_readonly`], but only if it is unique. Returns
+    /// whether it was, e.g. so that of the code paths that race to freeze a value, only the one
+    /// holding the only reference succeeds. A shared box is left unchanged.
+    ///
+    /// The check and the transition are a single update of the storage, so with the
+    /// `atomic-refcount` feature no other thread can clone the box in between.
+    pub fn try_mark_readonly(&self) -> bool {
+        self.update_storage(|storage| {
+            let is_unique = storage.is_unique();
+
+            if is_unique {
+                *storage = Storage::Readonly;
+            }
+
+            is_unique
+        })
+        .unwrap_or(false)
+    }
+
+    /// A readonly box with the same contents, e.g. to share a value widely without counting
+    /// references. Unless this box is readonly already, its contents are cloned into a new
+    /// allocation, which is never freed, like those of other readonly boxes.
+    pub fn clone_readonly(&self) -> Self
+    where
+        T: Clone,
+    {
+        if self.is_readonly() {
+            return self.clone();
+        }
+
+        let copy = Self::new(T::clone(self));
+        unsafe { copy.set_readonly() };
+
+        copy
+    }
+
+    /// A new, unique box with a clone of the contents. Unlike [`RocBox::clone`], which shares
+    /// the contents, changes to the new box are not seen through this one, and vice versa.
+    pub fn clone_inner(&self) -> Self
+    where
+        T: Clone,
+    {
+        Self::new(T::clone(self))
+    }
+
+    /// Keep the contents alive for the rest of the program, e.g. for a table that is built once
+    /// at startup. The box is marked readonly, so the other references to it no longer count
+    /// references, and dropping them never frees the allocation.
+    pub fn leak(self) -> &'static T
+    where
+        T: 'static,
+    {
+        let this = ManuallyDrop::new(self);
 
         unsafe {
-            &*self
-                .contents
-                .as_ptr()
-                .cast::<u8>()
-                .sub(alignment)
-                .cast::<Cell<Storage>>()
+            this.set_readonly();
+
+            &*this.contents.as_ptr()
+        }
+    }
+
+    /// A pointer to the contents, e.g. to lend them to C code. Unlike [`RocBox::into_raw`], the
+    /// box keeps ownership, so the pointer is only valid as long as the box is.
+    pub fn as_ptr(&self) -> *const T {
+        self.contents.as_ptr()
+    }
+
+    /// Compare the contents of two boxes with a custom comparator, e.g. to sort boxes by a key
+    /// when `T` does not implement `Ord`.
+    pub fn cmp_by<F>(&self, other: &Self, f: F) -> Ordering
+    where
+        F: Fn(&T, &T) -> Ordering,
+    {
+        f(self.deref(), other.deref())
+    }
+
+    /// Move the contents out of the box and free its allocation, if this is the only reference
+    /// to it. Like `Arc::try_unwrap`, the box is returned unchanged otherwise. Readonly boxes
+    /// are never unwrapped.
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        if !this.is_unique() && !this.is_dangling() {
+            return Err(this);
+        }
+
+        // The contents are moved out, so they must not be dropped with the box.
+        let this = ManuallyDrop::new(this);
+
+        unsafe {
+            let contents = ptr::read(this.contents.as_ptr());
+
+            if !this.is_dangling() {
+                Self::deallocate(this.contents, false);
+            }
+
+            Ok(contents)
         }
     }
 
-    // This is unsafe because before doing a deep_copy we need to check that
-    // self is read_only.
-    unsafe fn deep_copy(&self) -> Self {
-        let contents = unsafe { ptr::read(self.contents.as_ptr()) };
+    /// Move the contents out of the box, and free its allocation.
+    ///
+    /// # Panics
+    ///
+    /// If the box is not unique, i.e. other references to its contents exist, or it is readonly.
+    /// See [`RocBox::try_unwrap`] and [`RocBox::into_inner_or_clone`] for shared boxes.
+    pub fn into_inner(self) -> T {
+        match Self::try_unwrap(self) {
+            Ok(contents) => contents,
+            Err(_) => panic!("RocBox::into_inner called on a box that is shared or readonly"),
+        }
+    }
+
+    /// Like [`RocBox::into_inner`], but a box that is not unique is left to its other references,
+    /// and its contents are cloned instead.
+    pub fn into_inner_or_clone(self) -> T
+    where
+        T: Clone,
+    {
+        Self::try_unwrap(self).unwrap_or_else(|shared| T::clone(&shared))
+    }
+
+    /// A mutable reference to the contents, if this is the only reference to them. Like
+    /// `Arc::get_mut`, this returns `None` for a shared box. Readonly boxes may be referenced
+    /// from anywhere, so they are never mutable either, except for unallocated zero-sized
+    /// contents, which there is nothing to share of.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        if self.is_unique() || self.is_dangling() {
+            Some(unsafe { self.contents.as_mut() })
+        } else {
+            None
+        }
+    }
+
+    /// A mutable reference to the contents, like [`RocBox::get_mut`], but without checking the
+    /// reference count, e.g. in a hot loop over boxes that are known to be unique because they
+    /// were just created. Debug builds still check.
+    ///
+    /// # Safety
+    ///
+    /// The box must be unique. Mutating the contents of a shared box is undefined behavior,
+    /// since the other references may read them at the same time, and so is mutating a
+    /// readonly box, whose contents may be in read-only memory.
+    pub unsafe fn assume_unique_mut(&mut self) -> &mut T {
+        debug_assert!(
+            self.is_unique() || self.is_dangling(),
+            "RocBox::assume_unique_mut called on a box that is shared or readonly"
+        );
+
+        unsafe { self.contents.as_mut() }
+    }
+
+    /// Mutable access to the contents through a guard, if this is the only reference to them.
+    /// Like [`RocBox::get_mut`] this is `None` for shared and readonly boxes. The guard borrows
+    /// the box mutably, so it can't be cloned while the guard is alive; debug builds check on
+    /// drop that it is still unique.
+    pub fn borrow_mut(&mut self) -> Option<RocBoxMutGuard<'_, T>> {
+        if self.is_unique() || self.is_dangling() {
+            Some(RocBoxMutGuard { roc_box: self })
+        } else {
+            None
+        }
+    }
+
+    /// A view of the contents that can be passed around without touching the reference count,
+    /// unlike a clone of the box. Use [`RocBoxRef::to_owned`] where a box is needed after all.
+    pub fn borrow_ref(&self) -> RocBoxRef<'_, T> {
+        RocBoxRef {
+            contents: self.contents,
+            _marker: PhantomData,
+        }
+    }
+
+    /// A mutable reference to the contents, which are copied on write: a shared (or readonly)
+    /// box first moves to a new allocation with a clone of the contents, like `Arc::make_mut`.
+    /// The other references keep seeing the old contents.
+    pub fn make_mut(&mut self) -> &mut T
+    where
+        T: Clone,
+    {
+        if !self.is_unique() {
+            // assigning drops the old reference, which decrements its reference count
+            *self = Self::new(T::clone(self));
+        }
+
+        unsafe { self.contents.as_mut() }
+    }
+
+    /// Move the contents of a std `Box` into a new `RocBox`. The two use different allocators
+    /// (`RocBox` goes through `roc_alloc`), so the contents are copied to a new allocation, and
+    /// the std box's allocation is freed.
+    #[allow(clippy::boxed_local)]
+    pub fn from_std_box(b: Box<T>) -> Self {
+        Self::new(*b)
+    }
+
+    /// Move the contents into a new std `Box`, and free this box's allocation. Like
+    /// [`RocBox::from_std_box`], this crosses allocators, so the contents are copied.
+    ///
+    /// # Panics
+    ///
+    /// If the box is not unique, see [`RocBox::into_inner`].
+    pub fn into_std_box(self) -> Box<T> {
+        Box::new(self.into_inner())
+    }
+
+    /// Put `value` in the box and return the previous contents.
+    ///
+    /// A unique box reuses its allocation. A shared (or readonly) box is copied on write: `self`
+    /// moves to a new allocation and the other references keep seeing the old contents.
+    pub fn replace(&mut self, value: T) -> T
+    where
+        T: Clone,
+    {
+        if self.is_unique() {
+            unsafe { ptr::replace(self.contents.as_ptr(), value) }
+        } else {
+            let old = T::clone(self);
+            *self = Self::new_with(|_| value);
+
+            old
+        }
+    }
+
+    /// Give up `n` references to this allocation at once, e.g. when a host has taken ownership
+    /// of values that Roc still counts as references.
+    ///
+    /// The reference held by `self` counts towards `n`. Returns `true` when no references are
+    /// left, in which case dropping `self` releases the allocation. Readonly boxes are not
+    /// reference counted, so for them this does nothing.
+    ///
+    /// # Safety
+    ///
+    /// `n` must not exceed the current reference count, and the references given up, other than
+    /// `self`, must not be used or dropped afterwards.
+    pub unsafe fn decrement_by(&self, n: usize) -> bool {
+        self.update_storage(|storage| storage.decrease_by(n))
+            .unwrap_or(false)
+    }
+
+    /// Whether this box has no allocation, because its contents are zero-sized, see
+    /// [`RocBox::allocate`]. Allocated contents are never at the dangling address, which is
+    /// smaller than the storage in front of them.
+    fn is_dangling(&self) -> bool {
+        mem::size_of::<T>() == 0 && self.contents == NonNull::dangling()
+    }
+
+    /// The storage in front of the contents, or `None` if there is no allocation.
+    fn load_storage(&self) -> Option<Storage> {
+        self.update_storage(|storage| *storage)
+    }
+
+    /// Apply `f` to the storage in front of the contents, or return `None` if there is no
+    /// allocation. Storage that `f` leaves unchanged is not written to, because readonly boxes
+    /// may live in read-only memory.
+    #[cfg(not(feature = "atomic-refcount"))]
+    fn update_storage<R>(&self, f: impl FnOnce(&mut Storage) -> R) -> Option<R> {
+        if self.is_dangling() {
+            return None;
+        }
+
+        let storage =
+            unsafe { &*Self::storage_ptr(self.contents.as_ptr()).cast::<Cell<Storage>>() };
+        let old_storage = storage.get();
+        let mut new_storage = old_storage;
+        let result = f(&mut new_storage);
+
+        if new_storage != old_storage {
+            storage.set(new_storage);
+        }
+
+        Some(result)
+    }
+
+    /// Apply `f` to the storage in front of the contents, or return `None` if there is no
+    /// allocation. Storage that `f` leaves unchanged is not written to, because readonly boxes
+    /// may live in read-only memory.
+    ///
+    /// The update is atomic, so boxes can be shared between threads. It acquires what other
+    /// threads released with their updates, so that whoever drops the contents sees all the
+    /// accesses made through other references first.
+    #[cfg(feature = "atomic-refcount")]
+    fn update_storage<R>(&self, mut f: impl FnMut(&mut Storage) -> R) -> Option<R> {
+        use core::sync::atomic::AtomicIsize;
+
+        if self.is_dangling() {
+            return None;
+        }
+
+        // Storage has the same size as isize (asserted in the storage module), and the
+        // allocation is aligned for both.
+        let storage = unsafe { &*Self::storage_ptr(self.contents.as_ptr()).cast::<AtomicIsize>() };
+        let mut old_raw = storage.load(AtomicOrdering::Acquire);
+
+        loop {
+            let mut new_storage = Storage::from_raw(old_raw);
+            let result = f(&mut new_storage);
+            let new_raw = new_storage.to_raw();
+
+            if new_raw == old_raw {
+                return Some(result);
+            }
+
+            match storage.compare_exchange_weak(
+                old_raw,
+                new_raw,
+                AtomicOrdering::AcqRel,
+                AtomicOrdering::Acquire,
+            ) {
+                Ok(_) => return Some(result),
+                Err(current_raw) => old_raw = current_raw,
+            }
+        }
+    }
+
+    /// A new box with a copy of the contents, made the way Roc copies values: bit for bit, after
+    /// which the reference counts of the values that the copy refers to are incremented. The
+    /// copy and the original then share those values, and each releases its reference to them.
+    ///
+    /// Contents that own memory without counting references to it, like a `Vec`, are not
+    /// [RocRefcounted]: a bitwise copy of them would free that memory twice. Only plain data,
+    /// whose `inc` does nothing, is copied bit for bit without further bookkeeping.
+    fn deep_copy(&self) -> Self
+    where
+        T: RocRefcounted,
+    {
+        let mut contents = unsafe { ptr::read(self.contents.as_ptr()) };
+        contents.inc();
+
         Self::new(contents)
     }
 }
 
+impl<T> RocBox<MaybeUninit<T>> {
+    /// Treat the contents of a box made by [`RocBox::new_uninit`] as initialized. From now on,
+    /// the contents are dropped with the last reference to them.
+    ///
+    /// # Safety
+    ///
+    /// The contents must have been initialized, like for `MaybeUninit::assume_init`.
+    pub unsafe fn assume_init(self) -> RocBox<T> {
+        // `MaybeUninit<T>` has the layout of `T`
+        unsafe { self.cast() }
+    }
+
+    /// Initialize the contents of a box made by [`RocBox::new_uninit`] with `value`, like
+    /// `Box::write`. Contents that were written before are overwritten without being dropped.
+    ///
+    /// # Panics
+    ///
+    /// If the box is shared or readonly, e.g. because it was cloned before it was initialized.
+    pub fn write(self, value: T) -> RocBox<T> {
+        if !self.is_unique() && !self.is_dangling() {
+            panic!("RocBox::write called on a box that is shared or readonly");
+        }
+
+        unsafe {
+            ptr::write(self.contents.as_ptr().cast::<T>(), value);
+
+            self.assume_init()
+        }
+    }
+}
+
+impl<E, const N: usize> RocBox<[E; N]> {
+    /// The boxed array as a slice, e.g. to pass it to code that takes slices of any length.
+    pub fn as_slice(&self) -> &[E] {
+        self.deref()
+    }
+}
+
+unsafe impl<T> RocRefcounted for RocBox<T>
+where
+    T: RocRefcounted,
+{
+    fn inc(&mut self) {
+        self.update_storage(Storage::increment_reference_count);
+    }
+
+    /// Like dropping the box: the last reference also drops the contents, which releases the
+    /// values that they refer to.
+    unsafe fn dec(&mut self) {
+        unsafe { ptr::drop_in_place(self) }
+    }
+
+    fn is_refcounted() -> bool {
+        true
+    }
+}
+
 impl<T> Deref for RocBox<T> {
     type Target = T;
 
@@ -126,69 +973,507 @@ where
     }
 }
 
+/// See [`RocBox::debug_full`]
+struct FullDebug<'a, T>(&'a RocBox<T>);
+
+impl<T> Debug for FullDebug<'_, T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RocBox")
+            .field("contents", self.0.deref())
+            .field("strong_count", &self.0.strong_count())
+            .field("readonly", &self.0.is_readonly())
+            .field("address", &self.0.contents)
+            .finish()
+    }
+}
+
+/// Hashes the contents, like `Box<T>`. This is consistent with `Eq` as long as `T`'s own impls
+/// are.
+impl<T> Hash for RocBox<T>
+where
+    T: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.deref().hash(state)
+    }
+}
+
+impl<T> Borrow<T> for RocBox<T> {
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+
+impl<T> AsRef<T> for RocBox<T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+/// Serializes the contents, like `Box<T>`
+#[cfg(feature = "serde")]
+impl<T: Serialize> Serialize for RocBox<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.deref().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for RocBox<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        T::deserialize(deserializer).map(RocBox::new)
+    }
+}
+
+/// Formats the address of the contents
+impl<T> fmt::Pointer for RocBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(&self.contents, f)
+    }
+}
+
 impl<T> Clone for RocBox<T> {
     fn clone(&self) -> Self {
-        let storage = self.storage();
-        let mut new_storage = storage.get();
-
         // Increment the reference count
-        if !new_storage.is_readonly() {
-            new_storage.increment_reference_count();
-            storage.set(new_storage);
-        }
+        self.update_storage(Storage::increment_reference_count);
 
         Self {
             contents: self.contents,
+            _marker: PhantomData,
         }
     }
 }
 
+/// The byte that the memory of a box is overwritten with when it is released in a debug build
+#[cfg(debug_assertions)]
+const POISON: u8 = 0xDE;
+
 impl<T> Drop for RocBox<T> {
     fn drop(&mut self) {
-        let storage = self.storage();
-        let contents = self.contents;
+        self.release(false)
+    }
+}
 
-        // Decrease the list's reference count.
-        let mut new_storage = storage.get();
-        let needs_dealloc = new_storage.decrease();
+impl<T> RocBox<T> {
+    /// Give up the reference held by `self`, freeing the allocation if it was the last one.
+    /// With `zeroize`, the contents are overwritten with zeros before the memory is released.
+    fn release(&mut self, zeroize: bool) {
+        // Without an allocation, there is nothing to drop or release.
+        // Decrease the box's reference count.
+        let needs_dealloc = match self.update_storage(Storage::decrease) {
+            Some(needs_dealloc) => needs_dealloc,
+            None => return,
+        };
+        let contents = self.contents;
 
         if needs_dealloc {
             unsafe {
-                // Drop the stored contents.
+                // Drop the stored contents where they are: pinned contents must not move
+                // before they are dropped.
                 let contents_ptr = contents.as_ptr();
 
-                mem::drop::<T>(ptr::read(contents_ptr));
+                ptr::drop_in_place(contents_ptr);
 
-                let alignment = Self::alloc_alignment();
+                if zeroize {
+                    Self::zeroize_in_place(contents_ptr);
+                }
 
-                // Release the memory.
-                roc_dealloc(
-                    contents.as_ptr().cast::<u8>().sub(alignment).cast(),
-                    alignment as u32,
-                );
+                Self::deallocate(contents, zeroize);
             }
-        } else if !new_storage.is_readonly() {
-            // Write the storage back.
-            storage.set(new_storage);
         }
     }
+
+    /// Overwrite the contents with zeros where they are. The contents are never moved or copied
+    /// before this, so no copy of a secret is left behind, e.g. on the stack.
+    ///
+    /// # Safety
+    ///
+    /// `contents` must point to contents that have been dropped, in an allocation that is about
+    /// to be freed.
+    unsafe fn zeroize_in_place(contents: *mut T) {
+        let bytes = contents.cast::<u8>();
+
+        // volatile, so that these writes to memory that is about to be freed are not optimized
+        // away
+        for offset in 0..mem::size_of::<T>() {
+            unsafe { ptr::write_volatile(bytes.add(offset), 0) };
+        }
+
+        compiler_fence(AtomicOrdering::SeqCst);
+    }
+
+    /// Release the memory of a box whose contents have already been dropped or moved out.
+    ///
+    /// # Safety
+    ///
+    /// `contents` must point into an allocation made by [`RocBox::allocate`] that no references
+    /// are left to.
+    unsafe fn deallocate(contents: NonNull<T>, zeroize: bool) {
+        let allocation = unsafe { Self::allocation_start(contents.as_ptr()) };
+
+        // In debug builds, make reads through a dangling pointer to this box obvious by
+        // overwriting the storage and contents. Zeroized boxes stay zeroed.
+        #[cfg(debug_assertions)]
+        if !zeroize {
+            let header_size = contents.as_ptr() as usize - allocation as usize;
+            let bytes = header_size + mem::size_of::<T>();
+
+            unsafe { ptr::write_bytes(allocation, POISON, bytes) };
+        }
+
+        #[cfg(not(debug_assertions))]
+        let _ = zeroize;
+
+        unsafe { roc_dealloc(allocation.cast(), Self::alloc_alignment() as u32) };
+    }
+}
+
+/// A [`RocBox`] that overwrites its contents with zeros when the last reference is dropped,
+/// see [`RocBox::new_zeroizing`].
+pub struct ZeroizingRocBox<T>(ManuallyDrop<RocBox<T>>);
+
+impl<T> Deref for ZeroizingRocBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.deref()
+    }
+}
+
+impl<T> Clone for ZeroizingRocBox<T> {
+    fn clone(&self) -> Self {
+        ZeroizingRocBox(ManuallyDrop::new(RocBox::clone(&self.0)))
+    }
+}
+
+impl<T> Debug for ZeroizingRocBox<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // don't print secrets
+        f.write_str("ZeroizingRocBox(..)")
+    }
+}
+
+impl<T> Drop for ZeroizingRocBox<T> {
+    fn drop(&mut self) {
+        self.0.release(true)
+    }
+}
+
+/// Mutable access to the contents of a unique [`RocBox`], see [`RocBox::borrow_mut`].
+pub struct RocBoxMutGuard<'a, T> {
+    roc_box: &'a mut RocBox<T>,
+}
+
+impl<T> Deref for RocBoxMutGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.roc_box.deref()
+    }
+}
+
+impl<T> DerefMut for RocBoxMutGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // The box was unique when the guard was created, and can't be cloned while it lives.
+        unsafe { self.roc_box.contents.as_mut() }
+    }
+}
+
+impl<T> Debug for RocBoxMutGuard<'_, T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
+impl<T> Drop for RocBoxMutGuard<'_, T> {
+    fn drop(&mut self) {
+        debug_assert!(
+            self.roc_box.is_unique() || self.roc_box.is_dangling(),
+            "A RocBox stopped being unique while its contents were borrowed mutably."
+        );
+    }
+}
+
+/// A view of the contents of a [`RocBox`] that does not own a reference to them, see
+/// [`RocBox::borrow_ref`]. It can't outlive the box it was borrowed from.
+pub struct RocBoxRef<'a, T> {
+    contents: NonNull<T>,
+    _marker: PhantomData<&'a RocBox<T>>,
+}
+
+impl<'a, T> RocBoxRef<'a, T> {
+    /// A box with the viewed contents, which increments their reference count, like cloning the
+    /// box that the view was borrowed from.
+    pub fn to_owned(&self) -> RocBox<T> {
+        // The box the view was borrowed from is alive, and owns a reference.
+        unsafe { RocBox::from_contents_ptr(self.contents, true) }
+    }
+
+    /// The contents, for as long as the box they were borrowed from
+    pub fn get(self) -> &'a T {
+        unsafe { self.contents.as_ref() }
+    }
+}
+
+impl<T> Clone for RocBoxRef<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for RocBoxRef<'_, T> {}
+
+impl<T> Deref for RocBoxRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.contents.as_ref() }
+    }
+}
+
+impl<T> Debug for RocBoxRef<'_, T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
+/// A [`RocBox`] that carries a 16-bit tag in its allocation, see [`RocBox::new_tagged`].
+#[repr(transparent)]
+pub struct TaggedRocBox<T>(RocBox<T>);
+
+impl<T> TaggedRocBox<T> {
+    pub fn tag(&self) -> u16 {
+        unsafe { ptr::read(RocBox::tag_ptr(self.0.contents)) }
+    }
+
+    /// Forget the tag. It stays in the allocation, which is freed as usual.
+    pub fn into_untagged(self) -> RocBox<T> {
+        self.0
+    }
+}
+
+impl<T> Deref for TaggedRocBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.deref()
+    }
+}
+
+impl<T> Clone for TaggedRocBox<T> {
+    fn clone(&self) -> Self {
+        TaggedRocBox(self.0.clone())
+    }
+}
+
+impl<T> Debug for TaggedRocBox<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TaggedRocBox")
+            .field("tag", &self.tag())
+            .field("contents", self.deref())
+            .finish()
+    }
+}
+
+/// Builds many boxes in a single allocation, which is much cheaper than allocating every box
+/// separately, e.g. for all the nodes of a large immutable tree.
+///
+/// The boxes it hands out are regular [`RocBox`]es with their own storage header, but they are
+/// readonly: neither Rust nor Roc code frees them individually. Instead the whole block, and the
+/// contents of its boxes, are released when the arena is dropped.
+pub struct RocBoxArena<T> {
+    /// The first slot, which is `padding` bytes into the allocation for over-aligned contents
+    block: NonNull<u8>,
+    padding: usize,
+    capacity: usize,
+    len: Cell<usize>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> RocBoxArena<T> {
+    /// Allocate room for `capacity` boxes up front. Once those are used up, [`RocBoxArena::alloc`]
+    /// falls back to allocating boxes separately.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (block, padding) = if capacity == 0 {
+            (NonNull::dangling(), 0)
+        } else {
+            let bytes = Self::slot_size()
+                .checked_mul(capacity)
+                .and_then(|bytes| bytes.checked_add(RocBox::<T>::alignment_padding()))
+                .expect("RocBoxArena capacity overflow");
+            let alignment = RocBox::<T>::alloc_alignment();
+
+            let ptr = unsafe { roc_alloc(bytes, alignment as u32) };
+
+            match NonNull::new(ptr.cast::<u8>()) {
+                Some(allocation) => {
+                    // align the slots of over-aligned contents, like `RocBox::place_contents`
+                    let padding = if RocBox::<T>::alignment_padding() == 0 {
+                        0
+                    } else {
+                        (allocation.as_ptr() as usize).wrapping_neg() & (alignment - 1)
+                    };
+
+                    let block = unsafe { NonNull::new_unchecked(allocation.as_ptr().add(padding)) };
+
+                    (block, padding)
+                }
+                None => allocation_failed(bytes, alignment as u32),
+            }
+        };
+
+        Self {
+            block,
+            padding,
+            capacity,
+            len: Cell::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Box `contents` in the next free slot of the arena.
+    ///
+    /// # Safety
+    ///
+    /// The arena owns the memory of the box, so the box and all of its clones must be dropped
+    /// before the arena is. The contents are dropped by the arena, so they must not be moved out
+    /// with [`RocBox::into_inner`].
+    pub unsafe fn alloc(&self, contents: T) -> RocBox<T> {
+        let index = self.len.get();
+
+        let contents_ptr = if index == self.capacity {
+            // the block is full, this box is freed as usual
+            RocBox::<T>::allocate(0)
+        } else {
+            unsafe {
+                let slot = self.block.as_ptr().add(index * Self::slot_size());
+                let contents = slot.add(RocBox::<T>::header_size()).cast::<T>();
+                RocBox::storage_ptr(contents).write(Storage::Readonly);
+
+                self.len.set(index + 1);
+
+                NonNull::new_unchecked(contents)
+            }
+        };
+
+        unsafe { ptr::write(contents_ptr.as_ptr(), contents) };
+
+        RocBox {
+            contents: contents_ptr,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The number of boxes that were allocated in the arena's block.
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Every slot is a storage header followed by the contents, padded so that the next header
+    /// is aligned again.
+    #[inline(always)]
+    fn slot_size() -> usize {
+        let alignment = RocBox::<T>::alloc_alignment();
+        let contents = (mem::size_of::<T>() + alignment - 1) / alignment * alignment;
+
+        RocBox::<T>::header_size() + contents
+    }
+}
+
+impl<T> Drop for RocBoxArena<T> {
+    fn drop(&mut self) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        unsafe {
+            for index in 0..self.len.get() {
+                let slot = self.block.as_ptr().add(index * Self::slot_size());
+                ptr::drop_in_place(slot.add(RocBox::<T>::header_size()).cast::<T>());
+            }
+
+            roc_dealloc(
+                self.block.as_ptr().sub(self.padding).cast(),
+                RocBox::<T>::alloc_alignment() as u32,
+            );
+        }
+    }
+}
+
+impl<T> Debug for RocBoxArena<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RocBoxArena")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity)
+            .finish()
+    }
 }
 
 // This is a RocBox that is checked to ensure it is unique or readonly such that it can be sent between threads safely.
 #[repr(transparent)]
 pub struct SendSafeRocBox<T>(RocBox<T>);
 
-unsafe impl<T> Send for SendSafeRocBox<T> {}
+// The contents move to the receiving thread, so they must be `Send`. A readonly box may share
+// them with boxes that stay behind, so they must be `Sync` too, like those of an `Arc`.
+unsafe impl<T: Send + Sync> Send for SendSafeRocBox<T> {}
+
+static_assertions::assert_impl_all!(SendSafeRocBox<u64>: Send);
+static_assertions::assert_not_impl_any!(SendSafeRocBox<alloc::rc::Rc<u8>>: Send);
+static_assertions::assert_not_impl_any!(SendSafeRocBox<core::cell::Cell<u8>>: Send);
+
+// Only contents that account for being copied, see [`RocBox::deep_copy`], can be copied into a
+// new send safe box.
+static_assertions::assert_not_impl_any!(SendSafeRocBox<Vec<u8>>: Clone, From<RocBox<Vec<u8>>>);
+
+// With atomic reference counts, a box can be cloned and dropped on any thread, like an `Arc`.
+#[cfg(feature = "atomic-refcount")]
+unsafe impl<T: Send + Sync> Send for RocBox<T> {}
+#[cfg(feature = "atomic-refcount")]
+unsafe impl<T: Send + Sync> Sync for RocBox<T> {}
+#[cfg(feature = "atomic-refcount")]
+unsafe impl<T: Send + Sync> Send for RocBoxRef<'_, T> {}
+#[cfg(feature = "atomic-refcount")]
+unsafe impl<T: Send + Sync> Sync for RocBoxRef<'_, T> {}
 
-impl<T> Clone for SendSafeRocBox<T> {
+#[cfg(not(feature = "atomic-refcount"))]
+static_assertions::assert_not_impl_any!(RocBox<u64>: Send, Sync);
+
+// Moving a box does not move its contents, see [`RocBox::pin`].
+impl<T> Unpin for RocBox<T> {}
+
+impl<T> Clone for SendSafeRocBox<T>
+where
+    T: RocRefcounted,
+{
     fn clone(&self) -> Self {
         // Determine if self is read only.
-        let is_readonly = {
-            let storage_ = self.0.storage().get();
-            let val = storage_.is_readonly();
-            self.0.storage().set(storage_);
-            val
-        };
+        let is_readonly = self.0.is_readonly();
 
         if is_readonly {
             // In this case we can just take ownership
@@ -196,21 +1481,19 @@ impl<T> Clone for SendSafeRocBox<T> {
             SendSafeRocBox(self.0.clone())
         } else {
             // This is not read only, do a deep copy.
-            unsafe { SendSafeRocBox(self.0.deep_copy()) }
+            SendSafeRocBox(self.0.deep_copy())
         }
     }
 }
 
-impl<T> From<RocBox<T>> for SendSafeRocBox<T> {
+impl<T> From<RocBox<T>> for SendSafeRocBox<T>
+where
+    T: RocRefcounted,
+{
     fn from(b: RocBox<T>) -> Self {
         // Determine if the give is read only or
         // if its reference count is 1.
-        let is_safe = {
-            let storage_ = b.storage().get();
-            let val = storage_.is_readonly() || storage_.is_unique();
-            b.storage().set(storage_);
-            val
-        };
+        let is_safe = b.is_readonly() || b.is_unique();
 
         if is_safe {
             // In this case we can just take ownership
@@ -218,7 +1501,7 @@ impl<T> From<RocBox<T>> for SendSafeRocBox<T> {
             SendSafeRocBox(b)
         } else {
             // This is not read only nor unique, do a deep copy.
-            unsafe { SendSafeRocBox(b.deep_copy()) }
+            SendSafeRocBox(b.deep_copy())
         }
     }
 }
@@ -228,3 +1511,49 @@ impl<T> From<SendSafeRocBox<T>> for RocBox<T> {
         ssb.0
     }
 }
+
+impl<T> SendSafeRocBox<T> {
+    /// The box inside, without converting back into a [RocBox].
+    ///
+    /// # Safety
+    ///
+    /// Unless the `atomic-refcount` feature is enabled, the box must not be cloned through this
+    /// reference while it is reference counted: the clone would share a reference count that is
+    /// not updated atomically with this box, which can still be sent to another thread.
+    pub unsafe fn as_roc_box(&self) -> &RocBox<T> {
+        &self.0
+    }
+}
+
+/// Reading the contents can't create more references to the box itself. Clones of the contents
+/// may stay behind when this is sent to another thread, hence the `Send` bound.
+impl<T> Deref for SendSafeRocBox<T>
+where
+    T: Send,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.deref()
+    }
+}
+
+impl<T> Debug for SendSafeRocBox<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T> PartialEq for SendSafeRocBox<T>
+where
+    T: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T> Eq for SendSafeRocBox<T> where T: Eq {}
@@ -13,7 +13,9 @@ use core::{
     ptr::{self, NonNull},
 };
 
-use crate::{roc_alloc, roc_dealloc, roc_realloc, storage::Storage};
+use crate::{
+    allocation_failed, roc_alloc, roc_dealloc, roc_realloc, storage::Storage, RocRefcounted,
+};
 
 #[cfg(feature = "serde")]
 use core::marker::PhantomData;
@@ -72,7 +74,7 @@ impl<T> RocList<T> {
         let alloc_ptr = unsafe { roc_alloc(Self::alloc_bytes(num_elems), Self::alloc_alignment()) };
 
         Self::elems_from_allocation(NonNull::new(alloc_ptr).unwrap_or_else(|| {
-            todo!("Call roc_panic with the info that an allocation failed.");
+            allocation_failed(Self::alloc_bytes(num_elems), Self::alloc_alignment())
         }))
     }
 
@@ -242,7 +244,7 @@ where
                     self.capacity_or_ref_ptr = new_len;
 
                     Self::elems_from_allocation(NonNull::new(new_ptr).unwrap_or_else(|| {
-                        todo!("Reallocation failed");
+                        allocation_failed(Self::alloc_bytes(new_len), Self::alloc_alignment())
                     }))
                 }
             } else {
@@ -325,7 +327,10 @@ impl<T> RocList<T> {
                             // no longer referencing them.
                             new_elems = Self::elems_from_allocation(
                                 NonNull::new(new_alloc).unwrap_or_else(|| {
-                                    todo!("Reallocation failed");
+                                    allocation_failed(
+                                        Self::alloc_bytes(new_len),
+                                        Self::alloc_alignment(),
+                                    )
                                 }),
                             );
                         }
@@ -525,6 +530,24 @@ impl<T> Drop for RocList<T> {
     }
 }
 
+unsafe impl<T> RocRefcounted for RocList<T>
+where
+    T: RocRefcounted,
+{
+    fn inc(&mut self) {
+        // cloning only increments the reference count of the list, not of its elements
+        mem::forget(self.clone())
+    }
+
+    unsafe fn dec(&mut self) {
+        unsafe { ptr::drop_in_place(self) }
+    }
+
+    fn is_refcounted() -> bool {
+        true
+    }
+}
+
 impl<T> From<&[T]> for RocList<T>
 where
     T: Clone,
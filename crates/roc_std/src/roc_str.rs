@@ -20,7 +20,7 @@ use core::{
 #[cfg(feature = "std")]
 use core::ffi::{CStr, CString};
 
-use crate::RocList;
+use crate::{RocList, RocRefcounted};
 
 #[repr(transparent)]
 pub struct RocStr(RocStrInner);
@@ -29,7 +29,7 @@ fn with_stack_bytes<F, E, T>(length: usize, closure: F) -> T
 where
     F: FnOnce(*mut E) -> T,
 {
-    use crate::{roc_alloc, roc_dealloc};
+    use crate::{allocation_failed, roc_alloc, roc_dealloc};
     use core::mem::MaybeUninit;
 
     if length < RocStr::TEMP_STR_MAX_STACK_BYTES {
@@ -44,6 +44,11 @@ where
         // The string is too long to stack-allocate, so
         // do a heap allocation and then free it afterwards.
         let ptr = unsafe { roc_alloc(length, align) } as *mut E;
+
+        if ptr.is_null() {
+            allocation_failed(length, align);
+        }
+
         let answer = closure(ptr);
 
         // Free the heap allocation.
@@ -662,6 +667,20 @@ impl Drop for RocStr {
     }
 }
 
+unsafe impl RocRefcounted for RocStr {
+    fn inc(&mut self) {
+        mem::forget(self.clone())
+    }
+
+    unsafe fn dec(&mut self) {
+        unsafe { ptr::drop_in_place(self) }
+    }
+
+    fn is_refcounted() -> bool {
+        true
+    }
+}
+
 // This is a RocStr that is checked to ensure it is unique or readonly such that it can be sent between threads safely.
 #[repr(transparent)]
 pub struct SendSafeRocStr(RocStr);
@@ -10,13 +10,51 @@ const REFCOUNT_1: NonZeroIsize = unsafe { NonZeroIsize::new_unchecked(isize::MIN
 const _ASSERT_STORAGE_SIZE: () =
     assert!(core::mem::size_of::<isize>() == core::mem::size_of::<Storage>());
 
-#[derive(Clone, Copy, Debug)]
+/// The reference count of an allocation, as reported by e.g. [`RocBox::refcount`](crate::RocBox::refcount)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Refcount {
+    /// The allocation is not reference counted, and is never freed.
+    Readonly,
+    /// The number of references to the allocation.
+    Count(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Storage {
     Readonly,
     ReferenceCounted(NonZeroIsize),
 }
 
 impl Storage {
+    /// The size of the header in front of the contents of a reference counted allocation, for
+    /// contents with the given alignment: the storage, rounded up to that alignment.
+    ///
+    /// The padding goes in front of the storage, so that the storage is always right before the
+    /// contents. That is where code generated by the Roc compiler reads the reference count.
+    pub const fn header_size(contents_alignment: usize) -> usize {
+        let size = core::mem::size_of::<Storage>();
+
+        (size + contents_alignment - 1) / contents_alignment * contents_alignment
+    }
+
+    /// The storage as it is laid out in memory: the reference count, or 0 when readonly.
+    #[cfg(feature = "atomic-refcount")]
+    pub(crate) fn to_raw(self) -> isize {
+        match self {
+            Storage::Readonly => 0,
+            Storage::ReferenceCounted(rc) => rc.get(),
+        }
+    }
+
+    /// The inverse of [`Storage::to_raw`].
+    #[cfg(feature = "atomic-refcount")]
+    pub(crate) fn from_raw(raw: isize) -> Self {
+        match NonZeroIsize::new(raw) {
+            Some(rc) => Storage::ReferenceCounted(rc),
+            None => Storage::Readonly,
+        }
+    }
+
     pub fn new_reference_counted() -> Self {
         Self::ReferenceCounted(REFCOUNT_1)
     }
@@ -56,6 +94,45 @@ impl Storage {
         }
     }
 
+    /// Give up `n` references in one step, the last of which is left to the final `decrease`
+    /// done while dropping: the count goes down by `n - 1`.
+    ///
+    /// Returns `true` if those were all the references, in which case the count is left at one,
+    /// so that the final `decrease` releases the memory.
+    pub fn decrease_by(&mut self, n: usize) -> bool {
+        match self {
+            Storage::Readonly => false,
+            Storage::ReferenceCounted(rc) => {
+                let remaining = Self::count(*rc);
+                let given_up = n.saturating_sub(1).min(remaining - 1);
+
+                *rc = NonZeroIsize::new(rc.get() - given_up as isize).expect("A reference count was decremented all the way to zero, which should never happen.");
+
+                n >= remaining
+            }
+        }
+    }
+
+    /// The number of references, or `None` if this is readonly and not reference counted.
+    pub fn reference_count(&self) -> Option<usize> {
+        match self {
+            Storage::Readonly => None,
+            Storage::ReferenceCounted(rc) => Some(Self::count(*rc)),
+        }
+    }
+
+    /// Like [`Storage::reference_count`], but readonly storage is reported explicitly.
+    pub fn refcount(&self) -> Refcount {
+        match self {
+            Storage::Readonly => Refcount::Readonly,
+            Storage::ReferenceCounted(rc) => Refcount::Count(Self::count(*rc)),
+        }
+    }
+
+    fn count(rc: NonZeroIsize) -> usize {
+        (rc.get() - REFCOUNT_1.get()) as usize + 1
+    }
+
     pub fn is_readonly(&self) -> bool {
         matches!(self, Self::Readonly)
     }
@@ -9,8 +9,26 @@ use core::ffi::c_void;
 
 const ROC_SMALL_STR_CAPACITY: usize = core::mem::size_of::<roc_std::RocStr>() - 1;
 
+std::thread_local! {
+    /// The size and alignment of the last allocation request.
+    static LAST_ALLOCATION: core::cell::Cell<Option<(usize, u32)>> = core::cell::Cell::new(None);
+
+    /// The number of allocation requests so far.
+    static ALLOCATIONS: core::cell::Cell<usize> = core::cell::Cell::new(0);
+
+    /// When set, allocation requests fail, see [`with_failing_allocations`].
+    static FAIL_ALLOCATIONS: core::cell::Cell<bool> = core::cell::Cell::new(false);
+}
+
 #[no_mangle]
-pub unsafe extern "C" fn roc_alloc(size: usize, _alignment: u32) -> *mut c_void {
+pub unsafe extern "C" fn roc_alloc(size: usize, alignment: u32) -> *mut c_void {
+    LAST_ALLOCATION.with(|last| last.set(Some((size, alignment))));
+    ALLOCATIONS.with(|count| count.set(count.get() + 1));
+
+    if FAIL_ALLOCATIONS.with(|fail| fail.get()) {
+        return core::ptr::null_mut();
+    }
+
     libc::malloc(size)
 }
 
@@ -24,9 +42,64 @@ pub unsafe extern "C" fn roc_realloc(
     libc::realloc(c_ptr, new_size)
 }
 
+std::thread_local! {
+    /// When set, deallocated blocks are not freed but kept here, so tests can inspect them.
+    static RETAINED_BLOCKS: core::cell::RefCell<Option<Vec<*mut c_void>>> =
+        core::cell::RefCell::new(None);
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn roc_dealloc(c_ptr: *mut c_void, _alignment: u32) {
-    libc::free(c_ptr)
+    let retained = RETAINED_BLOCKS.with(|blocks| match blocks.borrow_mut().as_mut() {
+        Some(blocks) => {
+            blocks.push(c_ptr);
+            true
+        }
+        None => false,
+    });
+
+    if !retained {
+        libc::free(c_ptr)
+    }
+}
+
+/// Run `f`, and return the blocks it deallocated. They are freed once the caller is done.
+fn with_retained_blocks(f: impl FnOnce(), inspect: impl FnOnce(&[*mut c_void])) {
+    RETAINED_BLOCKS.with(|blocks| *blocks.borrow_mut() = Some(Vec::new()));
+    f();
+    let blocks = RETAINED_BLOCKS.with(|blocks| blocks.borrow_mut().take().unwrap());
+
+    inspect(&blocks);
+
+    for block in blocks {
+        unsafe { libc::free(block) }
+    }
+}
+
+std::thread_local! {
+    /// When set, the messages passed to `roc_panic` are kept here, and it returns instead of
+    /// panicking, see [`with_failing_allocations`].
+    static ROC_PANICS: core::cell::RefCell<Option<Vec<String>>> =
+        core::cell::RefCell::new(None);
+}
+
+/// Run `f` with an allocator that returns null, and return the messages that were passed to
+/// `roc_panic`. Panics are caught, since roc_std must not continue once `roc_panic` returns.
+fn with_failing_allocations(f: impl FnOnce() + std::panic::UnwindSafe) -> Vec<String> {
+    FAIL_ALLOCATIONS.with(|fail| fail.set(true));
+    ROC_PANICS.with(|panics| *panics.borrow_mut() = Some(Vec::new()));
+
+    let result = std::panic::catch_unwind(f);
+
+    FAIL_ALLOCATIONS.with(|fail| fail.set(false));
+    let panics = ROC_PANICS.with(|panics| panics.borrow_mut().take().unwrap());
+
+    assert!(
+        result.is_err(),
+        "a failed allocation did not stop the program"
+    );
+
+    panics
 }
 
 #[cfg(test)]
@@ -39,7 +112,18 @@ pub unsafe extern "C" fn roc_panic(c_ptr: *mut c_void, tag_id: u32) {
         0 => {
             let c_str = CStr::from_ptr(c_ptr as *const c_char);
             let string = c_str.to_str().unwrap();
-            panic!("roc_panic during test: {}", string);
+
+            let recorded = ROC_PANICS.with(|panics| match panics.borrow_mut().as_mut() {
+                Some(panics) => {
+                    panics.push(string.to_string());
+                    true
+                }
+                None => false,
+            });
+
+            if !recorded {
+                panic!("roc_panic during test: {}", string);
+            }
         }
         _ => todo!(),
     }
@@ -161,6 +245,37 @@ mod test_roc_std {
         assert_eq!(orig, deserialized);
     }
 
+    #[test]
+    #[cfg(feature = "serde")]
+    fn box_serde_roundtrip() {
+        let orig = RocBox::new(RocStr::from("boxed"));
+
+        let serialized = serde_json::to_string(&orig).expect("failed to serialize box");
+        assert_eq!(serialized, r#""boxed""#);
+
+        let deserialized: RocBox<RocStr> =
+            serde_json::from_str(&serialized).expect("failed to deserialize box");
+
+        assert_eq!(orig, deserialized);
+        assert!(deserialized.is_unique());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn nested_box_serde_roundtrip() {
+        let orig = RocBox::new(RocBox::new(RocList::from_slice(&[1u8, 2, 3])));
+
+        let serialized = serde_json::to_string(&orig).expect("failed to serialize box");
+        assert_eq!(serialized, "[1,2,3]");
+
+        let deserialized: RocBox<RocBox<RocList<u8>>> =
+            serde_json::from_str(&serialized).expect("failed to deserialize box");
+
+        assert_eq!(orig, deserialized);
+        assert!(deserialized.is_unique());
+        assert!(RocBox::is_unique(&*deserialized));
+    }
+
     #[test]
     fn reserve_small_list() {
         let mut roc_list = RocList::<RocStr>::empty();
@@ -280,6 +395,1652 @@ mod test_roc_std {
         assert_eq!(roc_box.into_inner(), contents)
     }
 
+    #[test]
+    fn roc_box_cast_transparent() {
+        #[repr(transparent)]
+        #[derive(Debug, PartialEq)]
+        struct Meters(u64);
+
+        let roc_box = RocBox::new(Meters(42));
+        let inner: RocBox<u64> = unsafe { roc_box.cast() };
+        assert_eq!(*inner, 42);
+
+        let wrapped: RocBox<Meters> = unsafe { inner.cast() };
+        assert_eq!(*wrapped, Meters(42));
+    }
+
+    #[test]
+    fn roc_box_compare_readonly_and_refcounted() {
+        use core::cmp::Ordering;
+
+        let refcounted = RocBox::new(42i32);
+        let readonly = RocBox::new(42i32);
+        let bigger = RocBox::new(43i32);
+
+        // the box is leaked from here on
+        unsafe { readonly.set_readonly() };
+
+        assert!(readonly.is_readonly());
+        assert!(!refcounted.is_readonly());
+
+        for (a, b) in [(&refcounted, &readonly), (&readonly, &refcounted)] {
+            assert_eq!(a, b);
+            assert_eq!(a.cmp(b), Ordering::Equal);
+            assert_eq!(a.partial_cmp(b), Some(Ordering::Equal));
+        }
+
+        assert_eq!(readonly.cmp(&bigger), Ordering::Less);
+        assert_eq!(bigger.cmp(&readonly), Ordering::Greater);
+
+        unsafe { bigger.set_readonly() };
+        assert_eq!(readonly.cmp(&bigger), Ordering::Less);
+    }
+
+    #[test]
+    fn roc_box_cmp_by() {
+        #[derive(Debug)]
+        struct Record {
+            name: &'static str,
+            age: u32,
+        }
+
+        let mut boxes = vec![
+            RocBox::new(Record { name: "b", age: 30 }),
+            RocBox::new(Record { name: "c", age: 10 }),
+            RocBox::new(Record { name: "a", age: 20 }),
+        ];
+
+        boxes.sort_by(|a, b| a.cmp_by(b, |a, b| a.age.cmp(&b.age)));
+        let names: Vec<_> = boxes.iter().map(|record| record.name).collect();
+        assert_eq!(names, ["c", "a", "b"]);
+
+        boxes.sort_by(|a, b| a.cmp_by(b, |a, b| a.name.cmp(b.name)));
+        let names: Vec<_> = boxes.iter().map(|record| record.name).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn roc_box_new_from_fn() {
+        use crate::LAST_ALLOCATION;
+
+        let roc_box = RocBox::new(vec![1u64, 2]);
+        let layout = LAST_ALLOCATION.with(|last| last.get());
+
+        let from_fn = RocBox::new_from_fn(|| vec![1u64, 2]);
+        assert_eq!(LAST_ALLOCATION.with(|last| last.get()), layout);
+
+        assert_eq!(roc_box, from_fn);
+        assert!(from_fn.is_unique());
+        assert_eq!(from_fn.into_inner(), vec![1, 2]);
+    }
+
+    #[test]
+    fn roc_box_new_with_own_address() {
+        struct SelfAware {
+            address: *const SelfAware,
+            value: u32,
+        }
+
+        let mut seen = None;
+        let roc_box = RocBox::new_with(|contents| {
+            seen = Some(contents);
+
+            SelfAware {
+                address: contents.as_ptr(),
+                value: 42,
+            }
+        });
+
+        let address: *const SelfAware = &*roc_box;
+
+        assert_eq!(roc_box.address, address);
+        assert_eq!(seen.unwrap().as_ptr() as *const SelfAware, address);
+        assert_eq!(roc_box.value, 42);
+        assert!(roc_box.is_unique());
+    }
+
+    #[test]
+    fn roc_box_contents_layout() {
+        use crate::LAST_ALLOCATION;
+
+        fn check<T>(contents: T) {
+            let layout = RocBox::<T>::contents_layout();
+            let roc_box = RocBox::new(contents);
+
+            assert_eq!(
+                LAST_ALLOCATION.with(|last| last.get()),
+                Some((layout.size(), layout.align() as u32))
+            );
+
+            drop(roc_box);
+        }
+
+        check(1u8);
+        check(1u64);
+        check(1u128);
+        check([1u8; 3]);
+
+        // zero-sized contents are only allocated when they have to be dropped
+        struct Token;
+
+        impl Drop for Token {
+            fn drop(&mut self) {}
+        }
+
+        check(Token);
+
+        let layout = RocBox::<u8>::contents_layout();
+        assert_eq!(layout.align(), core::mem::align_of::<roc_std::Storage>());
+        assert_eq!(layout.size(), layout.align() + 1);
+    }
+
+    #[test]
+    fn roc_box_total_size() {
+        use crate::{ALLOCATIONS, LAST_ALLOCATION};
+
+        #[repr(align(32))]
+        #[allow(dead_code)]
+        struct Align32(u8);
+
+        fn check<T>(contents: T) {
+            let roc_box = RocBox::new(contents);
+
+            assert_eq!(
+                LAST_ALLOCATION
+                    .with(|last| last.get())
+                    .map(|(size, _)| size),
+                Some(RocBox::<T>::total_size())
+            );
+            assert_eq!(
+                RocBox::<T>::total_size(),
+                RocBox::<T>::contents_layout().size()
+            );
+
+            drop(roc_box);
+        }
+
+        check(1u8);
+        check(1u32);
+        check(1u64);
+        check(1u128);
+        check([1u16; 5]);
+        check(Align32(1));
+
+        // zero-sized contents without drop glue are not allocated
+        let before = ALLOCATIONS.with(|count| count.get());
+        let _unit = RocBox::new(());
+        assert_eq!(ALLOCATIONS.with(|count| count.get()), before);
+        assert_eq!(RocBox::<()>::total_size(), 0);
+    }
+
+    #[test]
+    fn roc_box_pin_drops_in_place() {
+        use core::cell::Cell;
+        use core::marker::PhantomPinned;
+
+        std::thread_local! {
+            static DROPPED_AT: Cell<usize> = const { Cell::new(0) };
+        }
+
+        struct Pinned {
+            _value: u64,
+            _pinned: PhantomPinned,
+        }
+
+        impl Drop for Pinned {
+            fn drop(&mut self) {
+                DROPPED_AT.with(|at| at.set(self as *const Pinned as usize));
+            }
+        }
+
+        let pinned = RocBox::pin(Pinned {
+            _value: 1,
+            _pinned: PhantomPinned,
+        });
+        let address = &*pinned as *const Pinned as usize;
+
+        let clone = pinned.clone();
+        drop(pinned);
+        assert_eq!(DROPPED_AT.with(|at| at.get()), 0);
+
+        drop(clone);
+        assert_eq!(DROPPED_AT.with(|at| at.get()), address);
+    }
+
+    #[test]
+    fn roc_box_variance() {
+        // compiles only if `RocBox<T>` is covariant in `T`
+        fn shorten<'a>(roc_box: RocBox<&'static str>) -> RocBox<&'a str> {
+            roc_box
+        }
+
+        let roc_box = shorten(RocBox::new("hello"));
+        assert_eq!(*roc_box, "hello");
+    }
+
+    #[test]
+    fn roc_box_pin() {
+        use core::cell::Cell;
+        use core::marker::PhantomPinned;
+
+        // points into itself once it is pinned
+        struct SelfRef {
+            value: u64,
+            this: Cell<*const u64>,
+            _pinned: PhantomPinned,
+        }
+
+        let pinned = RocBox::pin(SelfRef {
+            value: 42,
+            this: Cell::new(core::ptr::null()),
+            _pinned: PhantomPinned,
+        });
+        pinned.this.set(&pinned.value);
+
+        let check = |roc_box: &core::pin::Pin<RocBox<SelfRef>>| {
+            assert_eq!(roc_box.this.get(), &roc_box.value as *const u64);
+            assert_eq!(unsafe { *roc_box.this.get() }, 42);
+        };
+
+        check(&pinned);
+
+        let clone = pinned.clone();
+        check(&clone);
+
+        // moving the handles does not move the contents
+        let moved = vec![pinned, clone];
+        check(&moved[0]);
+        check(&moved[1]);
+
+        let mut moved = moved;
+        let last = moved.pop().unwrap();
+        drop(moved);
+        check(&last);
+    }
+
+    #[test]
+    fn roc_box_header_layout() {
+        use roc_std::Storage;
+
+        #[repr(align(16))]
+        struct Align16(u8);
+
+        #[repr(align(64))]
+        struct Align64(u8);
+
+        const STORAGE_SIZE: usize = core::mem::size_of::<Storage>();
+
+        assert_eq!(Storage::header_size(1), STORAGE_SIZE);
+        assert_eq!(Storage::header_size(8), STORAGE_SIZE);
+        assert_eq!(Storage::header_size(16), 16);
+        assert_eq!(Storage::header_size(64), 64);
+
+        fn check<T>(contents: T, header_size: usize) {
+            // contents aligned to more than 16 bytes are aligned by over-allocating
+            let padding = core::mem::align_of::<T>().saturating_sub(16);
+
+            let layout = RocBox::<T>::contents_layout();
+            assert_eq!(
+                layout.size(),
+                header_size + padding + core::mem::size_of::<T>()
+            );
+            assert_eq!(
+                layout.align(),
+                core::mem::align_of::<T>().max(core::mem::align_of::<Storage>())
+            );
+
+            let roc_box = RocBox::new(contents);
+            let _clone = roc_box.clone();
+            let contents = roc_box.as_ptr() as usize;
+            assert_eq!(contents % core::mem::align_of::<T>(), 0);
+
+            // the reference count is right before the contents, where generated code expects it
+            let refcount = unsafe { *((contents - STORAGE_SIZE) as *const isize) };
+            assert_eq!(refcount, isize::MIN + 1);
+        }
+
+        check(1u8, STORAGE_SIZE);
+        check(1u64, STORAGE_SIZE);
+        check(Align16(1), 16);
+        check(Align64(1), 64);
+    }
+
+    #[test]
+    fn roc_box_over_aligned() {
+        use crate::{with_retained_blocks, ALLOCATIONS};
+        use roc_std::RocBoxArena;
+
+        #[repr(align(32))]
+        struct Align32([u8; 40]);
+
+        #[repr(align(64))]
+        struct Align64(u64);
+
+        fn check<T>(make: impl Fn(u8) -> T, get: impl Fn(&T) -> u8) {
+            let alignment = core::mem::align_of::<T>();
+
+            // the test allocator is malloc, which only aligns to 16 bytes
+            for i in 0..16 {
+                let before = ALLOCATIONS.with(|count| count.get());
+                let roc_box = RocBox::new(make(i));
+                assert_eq!(ALLOCATIONS.with(|count| count.get()) - before, 1);
+
+                let contents = roc_box.as_ptr() as usize;
+                assert_eq!(contents % alignment, 0);
+                assert_eq!(get(&roc_box), i);
+
+                let clone = roc_box.clone();
+                assert_eq!(clone.strong_count(), Some(2));
+                drop(clone);
+
+                with_retained_blocks(
+                    || drop(roc_box),
+                    |blocks| {
+                        // the start of the allocation is found again
+                        assert_eq!(blocks.len(), 1);
+                        let offset = contents - blocks[0] as usize;
+                        assert!(offset >= alignment && offset <= 2 * alignment - 16);
+                    },
+                );
+            }
+
+            let tagged = RocBox::new_tagged(make(7), 3);
+            assert_eq!(&*tagged as *const T as usize % alignment, 0);
+            assert_eq!((get(&tagged), tagged.tag()), (7, 3));
+
+            let arena = RocBoxArena::with_capacity(4);
+            let boxes: Vec<RocBox<T>> = (0..4).map(|i| unsafe { arena.alloc(make(i)) }).collect();
+            for (i, roc_box) in boxes.iter().enumerate() {
+                assert_eq!(roc_box.as_ptr() as usize % alignment, 0);
+                assert_eq!(get(roc_box), i as u8);
+            }
+        }
+
+        check(|i| Align32([i; 40]), |a| a.0[39]);
+        check(|i| Align64(i as u64), |a| a.0 as u8);
+    }
+
+    #[test]
+    fn roc_box_eq() {
+        let roc_box = RocBox::new([1u64; 64]);
+        let clone = roc_box.clone();
+        let other = RocBox::new([1u64; 64]);
+        let different = RocBox::new([2u64; 64]);
+
+        assert!(RocBox::ptr_eq(&roc_box, &clone));
+        assert!(!RocBox::ptr_eq(&roc_box, &other));
+
+        assert_eq!(roc_box, clone);
+        assert_eq!(roc_box, other);
+        assert_ne!(roc_box, different);
+
+        assert!(RocBox::ptr_eq_or_eq(&roc_box, &clone));
+        assert!(RocBox::ptr_eq_or_eq(&roc_box, &other));
+        assert!(!RocBox::ptr_eq_or_eq(&roc_box, &different));
+    }
+
+    #[test]
+    fn roc_box_eq_nan() {
+        let nan = RocBox::new(f64::NAN);
+        let clone = nan.clone();
+
+        assert!(RocBox::ptr_eq(&nan, &clone));
+        assert_ne!(nan, clone);
+    }
+
+    #[test]
+    fn roc_box_try_send_safe() {
+        let unique = RocBox::new(1u64);
+        let send_safe = unique.try_send_safe().unwrap();
+        assert_eq!(*RocBox::from(send_safe), 1);
+
+        let readonly = RocBox::new(2u64);
+        unsafe { readonly.set_readonly() };
+        let _also_readonly = readonly.clone();
+        let send_safe = readonly.try_send_safe().unwrap();
+        assert_eq!(*RocBox::from(send_safe), 2);
+
+        let shared = RocBox::new(3u64);
+        let clone = shared.clone();
+        let shared = match shared.try_send_safe() {
+            Ok(_) => panic!("a shared box is not send safe"),
+            Err(shared) => shared,
+        };
+
+        assert_eq!(*shared, 3);
+        assert!(RocBox::ptr_eq(&shared, &clone));
+        assert!(!shared.is_unique());
+    }
+
+    #[test]
+    fn roc_box_borrow_mut() {
+        let mut roc_box = RocBox::new(vec![1u64]);
+
+        {
+            let mut guard = roc_box.borrow_mut().unwrap();
+            guard.push(2);
+            assert_eq!(*guard, vec![1, 2]);
+        }
+
+        assert_eq!(*roc_box, vec![1, 2]);
+
+        let clone = roc_box.clone();
+        assert!(roc_box.borrow_mut().is_none());
+        drop(clone);
+        assert!(roc_box.borrow_mut().is_some());
+
+        let mut readonly = RocBox::new(vec![3u64]).into_readonly();
+        assert!(readonly.borrow_mut().is_none());
+    }
+
+    #[test]
+    fn roc_box_into_readonly() {
+        use crate::{with_retained_blocks, ALLOCATIONS};
+        use roc_std::SendSafeRocBox;
+
+        with_retained_blocks(
+            || {
+                let roc_box = RocBox::new(1u64).into_readonly();
+                assert!(roc_box.is_readonly());
+
+                let clone = roc_box.clone();
+                assert!(RocBox::ptr_eq(&roc_box, &clone));
+                drop(clone);
+
+                let allocations = ALLOCATIONS.with(|count| count.get());
+                let send_safe = SendSafeRocBox::from(roc_box.clone());
+                assert_eq!(ALLOCATIONS.with(|count| count.get()), allocations);
+                assert_eq!(*RocBox::from(send_safe), 1);
+
+                drop(roc_box);
+            },
+            |blocks| assert!(blocks.is_empty()),
+        );
+    }
+
+    #[test]
+    fn roc_box_try_mark_readonly() {
+        let roc_box = RocBox::new(1u64);
+        assert!(roc_box.try_mark_readonly());
+        assert!(roc_box.is_readonly());
+
+        // marking it again fails, since readonly boxes are not unique
+        assert!(!roc_box.try_mark_readonly());
+
+        let shared = RocBox::new(2u64);
+        let clone = shared.clone();
+        assert!(!shared.try_mark_readonly());
+        assert!(!shared.is_readonly());
+        assert_eq!(shared.strong_count(), Some(2));
+
+        drop(clone);
+        assert!(shared.try_mark_readonly());
+    }
+
+    #[test]
+    fn send_safe_roc_box_read_on_other_thread() {
+        use roc_std::SendSafeRocBox;
+
+        let send_safe = SendSafeRocBox::from(RocBox::new(5u64));
+        let same = SendSafeRocBox::from(RocBox::new(5u64));
+        let different = SendSafeRocBox::from(RocBox::new(6u64));
+
+        let debug = std::thread::spawn(move || {
+            assert_eq!(*send_safe, 5);
+            assert_eq!(send_safe, same);
+            assert_ne!(send_safe, different);
+            assert!(unsafe { send_safe.as_roc_box() }.is_unique());
+
+            format!("{:?}", send_safe)
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(debug, "5");
+    }
+
+    #[test]
+    fn send_safe_roc_box_readonly_on_other_thread() {
+        let readonly = RocBox::new(String::from("shared")).into_readonly();
+        let send_safe = readonly.clone().try_send_safe().unwrap();
+
+        let len = std::thread::spawn(move || send_safe.len()).join().unwrap();
+
+        assert_eq!(len, 6);
+        assert_eq!(*readonly, "shared");
+    }
+
+    #[test]
+    fn roc_box_into_send_safe_vec() {
+        use crate::ALLOCATIONS;
+
+        let shared = RocBox::new(1u64);
+        let readonly = RocBox::new(2u64);
+        unsafe { readonly.set_readonly() };
+
+        let boxes = vec![
+            shared.clone(),
+            RocBox::new(3u64),
+            shared.clone(),
+            readonly.clone(),
+            shared.clone(),
+            RocBox::new(4u64),
+        ];
+
+        let before = ALLOCATIONS.with(|count| count.get());
+        let send_safe = RocBox::into_send_safe_vec(boxes);
+
+        // every clone of the shared allocation gets its own copy
+        assert_eq!(ALLOCATIONS.with(|count| count.get()) - before, 3);
+
+        let boxes: Vec<RocBox<u64>> = send_safe.into_iter().map(RocBox::from).collect();
+        assert_eq!(
+            boxes.iter().map(|b| **b).collect::<Vec<_>>(),
+            vec![1, 3, 1, 2, 1, 4]
+        );
+
+        // so that each of them can be sent to a different thread
+        for index in [0, 2, 4] {
+            assert!(boxes[index].is_unique());
+            assert!(!RocBox::ptr_eq(&boxes[index], &shared));
+        }
+        assert!(!RocBox::ptr_eq(&boxes[0], &boxes[2]));
+        assert!(!RocBox::ptr_eq(&boxes[2], &boxes[4]));
+        assert!(RocBox::ptr_eq(&boxes[3], &readonly));
+
+        // the batch released its references to the original
+        assert!(shared.is_unique());
+    }
+
+    #[test]
+    fn roc_box_get_mut() {
+        let mut roc_box = RocBox::new(1u64);
+        *roc_box.get_mut().unwrap() += 1;
+        assert_eq!(*roc_box, 2);
+
+        let clone = roc_box.clone();
+        assert_eq!(roc_box.get_mut(), None);
+
+        drop(clone);
+        *roc_box.get_mut().unwrap() += 1;
+        assert_eq!(*roc_box, 3);
+
+        let mut readonly = RocBox::new(4u64);
+        unsafe { readonly.set_readonly() };
+        assert_eq!(readonly.get_mut(), None);
+    }
+
+    #[test]
+    fn roc_box_get_mut_zero_sized() {
+        // zero-sized contents without drop glue are not allocated, but are still ours to mutate
+        let mut unit = RocBox::new(());
+        assert_eq!(unit.get_mut(), Some(&mut ()));
+        assert!(unit.borrow_mut().is_some());
+
+        let _clone = unit.clone();
+        assert_eq!(unit.get_mut(), Some(&mut ()));
+        assert!(unit.borrow_mut().is_some());
+    }
+
+    #[test]
+    fn roc_box_assume_unique_mut() {
+        let mut boxes: Vec<_> = (0..4u64).map(RocBox::new).collect();
+
+        for roc_box in boxes.iter_mut() {
+            // every box was just created, and none of them was cloned
+            *unsafe { roc_box.assume_unique_mut() } *= 10;
+        }
+
+        assert_eq!(
+            boxes.iter().map(|roc_box| **roc_box).collect::<Vec<_>>(),
+            vec![0, 10, 20, 30]
+        );
+        assert!(boxes.iter().all(RocBox::is_unique));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "shared or readonly")]
+    fn roc_box_assume_unique_mut_shared() {
+        let mut roc_box = RocBox::new(1u64);
+        let _clone = roc_box.clone();
+
+        unsafe { *roc_box.assume_unique_mut() += 1 };
+    }
+
+    #[test]
+    fn roc_box_as_slice() {
+        let roc_box = RocBox::new([1u64, 2, 3]);
+        let clone = roc_box.clone();
+
+        let slice: &[u64] = clone.as_slice();
+        assert_eq!(slice, &[1, 2, 3]);
+        assert_eq!(slice.as_ptr(), roc_box.as_slice().as_ptr());
+    }
+
+    #[test]
+    fn roc_box_borrow_ref() {
+        use roc_std::{Refcount, RocBoxRef};
+
+        fn outer(view: RocBoxRef<'_, RocStr>) -> usize {
+            middle(view) + middle(view)
+        }
+
+        fn middle(view: RocBoxRef<'_, RocStr>) -> usize {
+            inner(view)
+        }
+
+        fn inner(view: RocBoxRef<'_, RocStr>) -> usize {
+            view.len()
+        }
+
+        let roc_box = RocBox::new(RocStr::from("hello"));
+        let clone = roc_box.clone();
+        assert_eq!(roc_box.refcount(), Refcount::Count(2));
+
+        let view = roc_box.borrow_ref();
+        assert_eq!(outer(view), 10);
+        assert_eq!(view.get().as_str(), "hello");
+        assert_eq!(roc_box.refcount(), Refcount::Count(2));
+
+        // taking ownership after all is like cloning the box
+        let owned = view.to_owned();
+        assert!(RocBox::ptr_eq(&owned, &roc_box));
+        assert_eq!(roc_box.refcount(), Refcount::Count(3));
+
+        drop((owned, clone));
+        assert_eq!(roc_box.refcount(), Refcount::Count(1));
+    }
+
+    #[test]
+    fn roc_box_make_mut() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        struct Counted(u64, Rc<Cell<usize>>);
+
+        impl Clone for Counted {
+            fn clone(&self) -> Self {
+                self.1.set(self.1.get() + 1);
+                Counted(self.0, self.1.clone())
+            }
+        }
+
+        let clones = Rc::new(Cell::new(0));
+
+        // unique: mutated in place
+        let mut roc_box = RocBox::new(Counted(1, clones.clone()));
+        let address: *const Counted = &*roc_box;
+        roc_box.make_mut().0 += 1;
+        assert_eq!(roc_box.0, 2);
+        assert_eq!(clones.get(), 0);
+        assert_eq!(&*roc_box as *const Counted, address);
+
+        // shared: copied once, and the other reference still sees the old contents
+        let other = roc_box.clone();
+        roc_box.make_mut().0 += 1;
+        roc_box.make_mut().0 += 1;
+        assert_eq!(roc_box.0, 4);
+        assert_eq!(other.0, 2);
+        assert_eq!(clones.get(), 1);
+        assert!(roc_box.is_unique());
+        assert!(other.is_unique());
+
+        // readonly: always copied
+        let mut readonly = RocBox::new(Counted(5, clones.clone()));
+        unsafe { readonly.set_readonly() };
+        let original = readonly.clone();
+        readonly.make_mut().0 += 1;
+        assert_eq!(readonly.0, 6);
+        assert_eq!(original.0, 5);
+        assert_eq!(clones.get(), 2);
+        assert!(!readonly.is_readonly());
+    }
+
+    #[test]
+    fn roc_box_std_box_round_trip() {
+        use crate::{with_retained_blocks, ALLOCATIONS};
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        struct Counted(u64, Rc<Cell<usize>>);
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.1.set(self.1.get() + 1);
+            }
+        }
+
+        let drops = Rc::new(Cell::new(0));
+        let std_box = Box::new(Counted(42, drops.clone()));
+
+        let before = ALLOCATIONS.with(|count| count.get());
+        let roc_box = RocBox::from_std_box(std_box);
+        assert_eq!(ALLOCATIONS.with(|count| count.get()) - before, 1);
+        assert_eq!(roc_box.0, 42);
+
+        let mut std_box = None;
+        with_retained_blocks(
+            || std_box = Some(roc_box.into_std_box()),
+            |blocks| assert_eq!(blocks.len(), 1),
+        );
+
+        let std_box = std_box.unwrap();
+        assert_eq!(std_box.0, 42);
+        assert_eq!(drops.get(), 0);
+
+        drop(std_box);
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[test]
+    fn roc_box_hash_map_keys() {
+        use std::collections::HashMap;
+
+        let a = RocBox::new(1u64);
+        let mut map = HashMap::new();
+        map.insert(a.clone(), "a");
+        map.insert(RocBox::new(2u64), "b");
+
+        // an equal box, in another allocation, finds the same entry
+        assert_eq!(map.get(&RocBox::new(1u64)), Some(&"a"));
+        assert_eq!(map.get(&a), Some(&"a"));
+        // lookups by the contents go through Borrow
+        assert_eq!(map.get(&2u64), Some(&"b"));
+        assert_eq!(map.get(&3u64), None);
+
+        let readonly = RocBox::new(2u64);
+        unsafe { readonly.set_readonly() };
+        assert_eq!(map.insert(readonly, "c"), Some("b"));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn roc_box_ptr_eq_and_pointer() {
+        let a = RocBox::new(1u64);
+        let clone = a.clone();
+        let equal = RocBox::new(1u64);
+
+        assert!(RocBox::ptr_eq(&a, &clone));
+        assert!(!RocBox::ptr_eq(&a, &equal));
+        assert_eq!(a, equal);
+
+        let contents: &u64 = a.as_ref();
+        assert_eq!(format!("{:p}", a), format!("{:p}", contents));
+        assert_eq!(format!("{:p}", a), format!("{:p}", clone));
+        assert_ne!(format!("{:p}", a), format!("{:p}", equal));
+    }
+
+    #[test]
+    fn roc_box_strong_count() {
+        let roc_box = RocBox::new(1u64);
+        assert_eq!(roc_box.strong_count(), Some(1));
+
+        let clone = roc_box.clone();
+        let other_clone = clone.clone();
+        assert_eq!(roc_box.strong_count(), Some(3));
+
+        drop(clone);
+        assert_eq!(other_clone.strong_count(), Some(2));
+        drop(other_clone);
+        assert_eq!(roc_box.strong_count(), Some(1));
+
+        let readonly = RocBox::new(2u64);
+        unsafe { readonly.set_readonly() };
+        assert_eq!(readonly.strong_count(), None);
+    }
+
+    #[test]
+    fn roc_box_refcount() {
+        use roc_std::Refcount;
+
+        let roc_box = RocBox::new(1u64);
+        assert_eq!(roc_box.refcount(), Refcount::Count(1));
+        assert!(roc_box.is_unique());
+
+        let clone = roc_box.clone();
+        assert_eq!(roc_box.refcount(), Refcount::Count(2));
+        assert_eq!(clone.refcount(), Refcount::Count(2));
+        assert!(!roc_box.is_unique());
+
+        // inspecting doesn't change the count
+        assert_eq!(roc_box.refcount(), Refcount::Count(2));
+
+        drop(clone);
+        assert_eq!(roc_box.refcount(), Refcount::Count(1));
+        assert!(roc_box.is_unique());
+
+        let readonly = RocBox::new(2u64).into_readonly();
+        let readonly_clone = readonly.clone();
+        assert_eq!(readonly.refcount(), Refcount::Readonly);
+        assert_eq!(readonly_clone.refcount(), Refcount::Readonly);
+        assert!(!readonly.is_unique());
+
+        assert_eq!(RocBox::new(()).refcount(), Refcount::Readonly);
+    }
+
+    #[test]
+    #[cfg(feature = "atomic-refcount")]
+    fn roc_box_concurrent_clone_and_drop() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Counted(u64);
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let roc_box = RocBox::new(Counted(42));
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..1000 {
+                        let clone = roc_box.clone();
+                        assert_eq!(clone.0, 42);
+                        drop(clone);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(roc_box.strong_count(), Some(1));
+        assert_eq!(DROPS.load(Ordering::SeqCst), 0);
+
+        // The last reference can be dropped on another thread.
+        let clones: Vec<_> = (0..8).map(|_| roc_box.clone()).collect();
+        drop(roc_box);
+
+        std::thread::scope(|scope| {
+            for clone in clones {
+                scope.spawn(move || drop(clone));
+            }
+        });
+
+        assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn roc_box_debug_full() {
+        let roc_box = RocBox::new(7u64);
+        let clone = roc_box.clone();
+
+        let debug = format!("{:?}", roc_box.debug_full());
+        assert!(debug.starts_with(
+            "RocBox { contents: 7, strong_count: Some(2), readonly: false, address: 0x"
+        ));
+        assert_eq!(format!("{:?}", roc_box), "7");
+
+        drop(clone);
+        assert!(format!("{:?}", roc_box.debug_full()).contains("strong_count: Some(1)"));
+
+        let readonly = RocBox::new(8u64);
+        unsafe { readonly.set_readonly() };
+        assert!(
+            format!("{:?}", readonly.debug_full()).contains("strong_count: None, readonly: true")
+        );
+    }
+
+    #[test]
+    fn roc_box_raw_round_trip() {
+        use crate::with_retained_blocks;
+
+        let raw = RocBox::into_raw(RocBox::new(1u64));
+        let roc_box = unsafe { RocBox::from_raw(raw) };
+
+        assert_eq!(*roc_box, 1);
+        assert!(roc_box.is_unique());
+
+        with_retained_blocks(|| drop(roc_box), |blocks| assert_eq!(blocks.len(), 1));
+    }
+
+    #[test]
+    fn roc_box_raw_round_trip_shared() {
+        let roc_box = RocBox::new(1u64);
+        let clone = roc_box.clone();
+
+        let raw = RocBox::into_raw(clone);
+        assert_eq!(roc_box.strong_count(), Some(2));
+        assert_eq!(raw as *const u64, &*roc_box as *const u64);
+
+        let clone = unsafe { RocBox::from_raw(raw) };
+        assert!(RocBox::ptr_eq(&roc_box, &clone));
+
+        drop(clone);
+        assert!(roc_box.is_unique());
+    }
+
+    #[test]
+    fn roc_box_leak() {
+        use crate::with_retained_blocks;
+
+        let roc_box = RocBox::new(1u64);
+        let clone = roc_box.clone();
+        let address = roc_box.as_ptr();
+
+        let leaked: &'static u64 = roc_box.leak();
+        assert_eq!(*leaked, 1);
+        assert_eq!(leaked as *const u64, address);
+        assert!(clone.is_readonly());
+
+        with_retained_blocks(
+            || {
+                for _ in 0..100 {
+                    drop(clone.clone());
+                }
+
+                drop(clone);
+            },
+            |blocks| assert!(blocks.is_empty()),
+        );
+
+        assert_eq!(*leaked, 1);
+    }
+
+    #[test]
+    fn roc_box_from_contents_ptr() {
+        use core::ptr::NonNull;
+
+        let roc_box = RocBox::new(1u64);
+        let ptr = NonNull::new(roc_box.as_ptr() as *mut u64).unwrap();
+
+        // a new owner
+        let clone = unsafe { RocBox::from_contents_ptr(ptr, true) };
+        assert!(RocBox::ptr_eq(&roc_box, &clone));
+        assert_eq!(roc_box.strong_count(), Some(2));
+
+        // a transferred reference
+        let raw = RocBox::into_raw(clone);
+        let clone = unsafe { RocBox::from_contents_ptr(NonNull::new(raw).unwrap(), false) };
+        assert_eq!(roc_box.strong_count(), Some(2));
+
+        drop(clone);
+        assert_eq!(roc_box.strong_count(), Some(1));
+    }
+
+    #[test]
+    fn roc_box_zero_sized() {
+        use crate::{with_retained_blocks, ALLOCATIONS};
+        use roc_std::SendSafeRocBox;
+
+        let before = ALLOCATIONS.with(|count| count.get());
+
+        with_retained_blocks(
+            || {
+                let roc_box = RocBox::new(());
+
+                for _ in 0..10 {
+                    let clone = roc_box.clone();
+                    assert_eq!(*clone, ());
+                    drop(clone);
+                }
+
+                assert!(roc_box.is_readonly());
+                assert_eq!(roc_box.strong_count(), None);
+
+                let send_safe = roc_box.clone().try_send_safe().ok().unwrap();
+                assert_eq!(*RocBox::from(send_safe), ());
+                assert_eq!(*RocBox::from(SendSafeRocBox::from(roc_box.clone())), ());
+
+                roc_box.into_inner();
+            },
+            |blocks| assert!(blocks.is_empty()),
+        );
+
+        assert_eq!(ALLOCATIONS.with(|count| count.get()), before);
+    }
+
+    #[test]
+    fn roc_box_zero_sized_with_drop() {
+        use std::cell::Cell;
+
+        std::thread_local! {
+            static DROPS: Cell<usize> = Cell::new(0);
+        }
+
+        struct Token;
+
+        impl Drop for Token {
+            fn drop(&mut self) {
+                DROPS.with(|drops| drops.set(drops.get() + 1));
+            }
+        }
+
+        let roc_box = RocBox::new(Token);
+        let clone = roc_box.clone();
+
+        drop(clone);
+        assert_eq!(DROPS.with(|drops| drops.get()), 0);
+        drop(roc_box);
+        assert_eq!(DROPS.with(|drops| drops.get()), 1);
+
+        let token = RocBox::new(Token).into_inner();
+        assert_eq!(DROPS.with(|drops| drops.get()), 1);
+        drop(token);
+        assert_eq!(DROPS.with(|drops| drops.get()), 2);
+    }
+
+    #[test]
+    fn roc_box_clone_readonly() {
+        let roc_box = RocBox::new(1u64);
+        let readonly = roc_box.clone_readonly();
+
+        assert_eq!(*readonly, 1);
+        assert!(readonly.is_readonly());
+        assert!(!RocBox::ptr_eq(&roc_box, &readonly));
+        assert!(roc_box.is_unique());
+
+        let again = readonly.clone_readonly();
+        assert!(RocBox::ptr_eq(&readonly, &again));
+        assert!(again.is_readonly());
+    }
+
+    #[test]
+    fn roc_box_clone_inner() {
+        use std::cell::Cell;
+
+        std::thread_local! {
+            static DROPS: Cell<usize> = Cell::new(0);
+        }
+
+        #[derive(Clone)]
+        struct Counted(Vec<u64>);
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.with(|drops| drops.set(drops.get() + 1));
+            }
+        }
+
+        let original = RocBox::new(Counted(vec![1, 2]));
+        let mut copy = original.clone_inner();
+
+        assert!(!RocBox::ptr_eq(&original, &copy));
+        assert!(original.is_unique());
+        assert!(copy.is_unique());
+
+        copy.get_mut().unwrap().0.push(3);
+        assert_eq!(original.0, vec![1, 2]);
+        assert_eq!(copy.0, vec![1, 2, 3]);
+
+        drop((original, copy));
+        assert_eq!(DROPS.with(|drops| drops.get()), 2);
+    }
+
+    #[test]
+    fn roc_box_nested_refcounts() {
+        use roc_std::{RocRefcounted, SendSafeRocBox};
+
+        let leaf = RocBox::new(1u64);
+        let middle = RocBox::new(leaf.clone());
+        let outer = RocBox::new(middle.clone());
+        assert_eq!(leaf.strong_count(), Some(2));
+        assert_eq!(middle.strong_count(), Some(2));
+
+        // copying a shared box copies its contents bit for bit, and increments their count
+        let send_safe = SendSafeRocBox::from(outer.clone());
+        assert!(outer.is_unique());
+        assert_eq!(middle.strong_count(), Some(3));
+        assert_eq!(leaf.strong_count(), Some(2));
+
+        let send_safe_copy = send_safe.clone();
+        assert_eq!(middle.strong_count(), Some(4));
+
+        drop((send_safe, send_safe_copy));
+        assert_eq!(middle.strong_count(), Some(2));
+
+        // releasing the last reference to the outer box releases what it refers to
+        drop(outer);
+        assert_eq!(middle.strong_count(), Some(1));
+        drop(middle);
+        assert_eq!(leaf.strong_count(), Some(1));
+
+        // like Roc code, account for a bit for bit copy with `inc`, and give it up with `dec`
+        let mut list = RocBox::new(RocList::from_slice(&[RocStr::from(
+            "a string that is too long to be a small string",
+        )]));
+        let mut copy = unsafe { std::ptr::read(&list) };
+        copy.inc();
+        assert_eq!(list.strong_count(), Some(2));
+
+        unsafe { copy.dec() };
+        std::mem::forget(copy);
+        assert!(list.is_unique());
+        assert!(list.get_mut().unwrap().is_unique());
+        assert!(list[0].is_unique());
+
+        assert!(RocBox::<RocList<RocStr>>::is_refcounted());
+        assert!(!u64::is_refcounted());
+    }
+
+    #[test]
+    fn send_safe_roc_box_copies_free_once() {
+        use crate::with_retained_blocks;
+        use roc_std::SendSafeRocBox;
+
+        with_retained_blocks(
+            || {
+                let original = RocBox::new(RocList::from_slice(&[1u64, 2, 3]));
+
+                // both are copies of a shared box, which share the list
+                let send_safe = SendSafeRocBox::from(original.clone());
+                let copy = send_safe.clone();
+                assert_eq!(unsafe { copy.as_roc_box() }.as_slice(), &[1, 2, 3]);
+
+                drop((original, send_safe, copy));
+            },
+            |blocks| {
+                // three boxes and one list, each freed exactly once
+                let mut distinct = blocks.to_vec();
+                distinct.sort();
+                distinct.dedup();
+
+                assert_eq!(blocks.len(), 4);
+                assert_eq!(distinct.len(), 4);
+            },
+        );
+    }
+
+    #[test]
+    fn allocation_failure_calls_roc_panic() {
+        use crate::with_failing_allocations;
+
+        let size = RocBox::<u64>::contents_layout().size();
+        let panics = with_failing_allocations(|| drop(RocBox::new(1u64)));
+        assert_eq!(
+            panics,
+            vec![format!(
+                "Allocation of {} bytes with alignment 8 failed",
+                size
+            )]
+        );
+
+        let panics = with_failing_allocations(|| drop(RocList::<u32>::with_capacity(4)));
+        assert_eq!(panics.len(), 1);
+        assert!(panics[0].starts_with("Allocation of "));
+
+        // allocating works again afterwards
+        assert_eq!(*RocBox::new(2u64), 2);
+    }
+
+    #[test]
+    fn roc_box_try_new() {
+        use crate::{FAIL_ALLOCATIONS, LAST_ALLOCATION};
+        use roc_std::AllocError;
+        use std::cell::Cell;
+
+        std::thread_local! {
+            static DROPS: Cell<usize> = Cell::new(0);
+        }
+
+        struct Token(u64);
+
+        impl Drop for Token {
+            fn drop(&mut self) {
+                DROPS.with(|drops| drops.set(drops.get() + 1));
+            }
+        }
+
+        let roc_box = RocBox::try_new(Token(1)).unwrap();
+        assert_eq!(roc_box.0, 1);
+        drop(roc_box);
+        assert_eq!(DROPS.with(|drops| drops.get()), 1);
+
+        FAIL_ALLOCATIONS.with(|fail| fail.set(true));
+        let result = RocBox::try_new(Token(2));
+        FAIL_ALLOCATIONS.with(|fail| fail.set(false));
+
+        let layout = RocBox::<Token>::contents_layout();
+        assert_eq!(
+            LAST_ALLOCATION.with(|last| last.get()),
+            Some((layout.size(), layout.align() as u32))
+        );
+        assert_eq!(
+            result.err(),
+            Some(AllocError {
+                size: layout.size(),
+                alignment: layout.align() as u32,
+            })
+        );
+
+        // the contents were dropped exactly once, when the allocation failed
+        assert_eq!(DROPS.with(|drops| drops.get()), 2);
+    }
+
+    #[test]
+    fn roc_box_new_uninit() {
+        use crate::with_retained_blocks;
+        use std::cell::Cell;
+
+        std::thread_local! {
+            static DROPS: Cell<usize> = Cell::new(0);
+        }
+
+        struct Token(u64);
+
+        impl Drop for Token {
+            fn drop(&mut self) {
+                DROPS.with(|drops| drops.set(drops.get() + 1));
+            }
+        }
+
+        // dropped before it is initialized: the memory is freed, but no Token is dropped
+        with_retained_blocks(
+            || drop(RocBox::<Token>::new_uninit()),
+            |blocks| assert_eq!(blocks.len(), 1),
+        );
+        assert_eq!(DROPS.with(|drops| drops.get()), 0);
+
+        // filled in through a raw pointer, like C code would
+        let uninit = RocBox::<Token>::new_uninit();
+        unsafe { uninit.as_ptr().cast_mut().cast::<Token>().write(Token(1)) };
+        let roc_box = unsafe { uninit.assume_init() };
+        assert_eq!(roc_box.0, 1);
+        assert!(roc_box.is_unique());
+        assert_eq!(DROPS.with(|drops| drops.get()), 0);
+
+        with_retained_blocks(|| drop(roc_box), |blocks| assert_eq!(blocks.len(), 1));
+        assert_eq!(DROPS.with(|drops| drops.get()), 1);
+
+        let roc_box = RocBox::<Token>::new_uninit().write(Token(2));
+        assert_eq!(roc_box.0, 2);
+        drop(roc_box);
+        assert_eq!(DROPS.with(|drops| drops.get()), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "shared or readonly")]
+    fn roc_box_write_shared() {
+        let uninit = RocBox::<u64>::new_uninit();
+        let _other = uninit.clone();
+
+        uninit.write(1);
+    }
+
+    #[test]
+    fn roc_box_replace_unique() {
+        use crate::LAST_ALLOCATION;
+
+        let mut roc_box = RocBox::new(1u64);
+        let address: *const u64 = &*roc_box;
+
+        LAST_ALLOCATION.with(|last| last.set(None));
+
+        assert_eq!(roc_box.replace(2), 1);
+        assert_eq!(*roc_box, 2);
+
+        // the allocation was reused
+        assert_eq!(LAST_ALLOCATION.with(|last| last.get()), None);
+        assert_eq!(&*roc_box as *const u64, address);
+        assert!(roc_box.is_unique());
+    }
+
+    #[test]
+    fn roc_box_replace_shared() {
+        use crate::LAST_ALLOCATION;
+
+        let mut roc_box = RocBox::new(1u64);
+        let shared = roc_box.clone();
+
+        LAST_ALLOCATION.with(|last| last.set(None));
+
+        assert_eq!(roc_box.replace(2), 1);
+        assert_eq!(*roc_box, 2);
+        assert_eq!(*shared, 1);
+
+        // the mutated box moved to a new allocation
+        assert!(LAST_ALLOCATION.with(|last| last.get()).is_some());
+        assert_ne!(&*roc_box as *const u64, &*shared as *const u64);
+        assert!(roc_box.is_unique());
+        assert!(shared.is_unique());
+    }
+
+    #[test]
+    fn tagged_roc_box() {
+        use crate::with_retained_blocks;
+
+        let tagged = RocBox::new_tagged(42u64, 7);
+        assert_eq!(tagged.tag(), 7);
+        assert_eq!(*tagged, 42);
+
+        let clone = tagged.clone();
+        assert_eq!(clone.tag(), 7);
+
+        // odd sizes put the tag at an aligned offset
+        let bytes = RocBox::new_tagged([1u8, 2, 3], u16::MAX);
+        assert_eq!(bytes.tag(), u16::MAX);
+        assert_eq!(*bytes, [1, 2, 3]);
+
+        let zero_sized = RocBox::new_tagged((), 3);
+        assert_eq!(zero_sized.tag(), 3);
+
+        with_retained_blocks(|| drop(tagged), |blocks| assert!(blocks.is_empty()));
+
+        with_retained_blocks(
+            || drop(clone.into_untagged()),
+            |blocks| assert_eq!(blocks.len(), 1),
+        );
+    }
+
+    #[test]
+    fn roc_box_arena() {
+        use crate::with_retained_blocks;
+        use roc_std::RocBoxArena;
+        use std::rc::Rc;
+
+        let counter = Rc::new(());
+        let arena = RocBoxArena::with_capacity(3);
+
+        let boxes: Vec<RocBox<(u8, Rc<()>)>> = (0..4)
+            .map(|i| unsafe { arena.alloc((i, counter.clone())) })
+            .collect();
+
+        assert_eq!(arena.len(), 3);
+        assert_eq!(Rc::strong_count(&counter), 5);
+
+        for (i, roc_box) in boxes.iter().enumerate() {
+            assert_eq!(roc_box.0, i as u8);
+        }
+
+        // the boxes in the arena are readonly, the one that did not fit is not
+        assert!(boxes[..3].iter().all(|roc_box| roc_box.is_readonly()));
+        assert!(boxes[3].is_unique());
+
+        with_retained_blocks(|| drop(boxes), |blocks| assert_eq!(blocks.len(), 1));
+
+        // the arena still owns the contents of its boxes
+        assert_eq!(Rc::strong_count(&counter), 4);
+
+        with_retained_blocks(|| drop(arena), |blocks| assert_eq!(blocks.len(), 1));
+
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn roc_box_arena_alignment() {
+        use roc_std::RocBoxArena;
+
+        let arena = RocBoxArena::with_capacity(4);
+        let bytes: Vec<RocBox<[u8; 3]>> = (0..4).map(|i| unsafe { arena.alloc([i; 3]) }).collect();
+
+        for (i, roc_box) in bytes.iter().enumerate() {
+            assert_eq!(**roc_box, [i as u8; 3]);
+        }
+
+        drop(bytes);
+
+        let arena = RocBoxArena::with_capacity(4);
+        let wide: Vec<RocBox<u128>> = (0..4).map(|i| unsafe { arena.alloc(i) }).collect();
+
+        for (i, roc_box) in wide.iter().enumerate() {
+            assert_eq!(**roc_box, i as u128);
+            assert_eq!(
+                &**roc_box as *const u128 as usize % core::mem::align_of::<u128>(),
+                0
+            );
+        }
+    }
+
+    #[test]
+    fn zeroizing_roc_box_drops_in_place() {
+        use core::cell::Cell;
+
+        std::thread_local! {
+            static DROPPED_AT: Cell<usize> = const { Cell::new(0) };
+        }
+
+        struct Secret([u8; 32]);
+
+        impl Drop for Secret {
+            fn drop(&mut self) {
+                DROPPED_AT.with(|at| at.set(self.0.as_ptr() as usize));
+            }
+        }
+
+        let secret = RocBox::new_zeroizing(Secret([0xAB; 32]));
+        let address = secret.0.as_ptr() as usize;
+
+        // the secret is dropped and zeroed where it is, without being copied first
+        drop(secret);
+        assert_eq!(DROPPED_AT.with(|at| at.get()), address);
+    }
+
+    #[test]
+    fn zeroizing_roc_box() {
+        use crate::with_retained_blocks;
+
+        let secret = RocBox::new_zeroizing([0xABu8; 32]);
+        let contents = &*secret as *const [u8; 32];
+        let shared = secret.clone();
+
+        with_retained_blocks(
+            || drop(secret),
+            |blocks| {
+                // another reference is left, nothing is zeroed or freed
+                assert!(blocks.is_empty());
+                assert_eq!(unsafe { *contents }, [0xAB; 32]);
+            },
+        );
+
+        with_retained_blocks(
+            || drop(shared),
+            |blocks| {
+                assert_eq!(blocks.len(), 1);
+                assert_eq!(unsafe { *contents }, [0; 32]);
+            },
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn roc_box_poisons_released_memory() {
+        use crate::with_retained_blocks;
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        // records the value it holds when it is dropped
+        struct Witness(u64, Rc<Cell<u64>>);
+
+        impl Drop for Witness {
+            fn drop(&mut self) {
+                self.1.set(self.0);
+            }
+        }
+
+        let seen = Rc::new(Cell::new(0));
+        let roc_box = RocBox::new_with(|_| Witness(0x1234, seen.clone()));
+        let layout = RocBox::<Witness>::contents_layout();
+
+        with_retained_blocks(
+            || drop(roc_box),
+            |blocks| {
+                // the destructor ran before the memory was poisoned
+                assert_eq!(seen.get(), 0x1234);
+
+                assert_eq!(blocks.len(), 1);
+                let bytes =
+                    unsafe { core::slice::from_raw_parts(blocks[0] as *const u8, layout.size()) };
+                assert!(bytes.iter().all(|byte| *byte == 0xDE), "{:x?}", bytes);
+            },
+        );
+    }
+
+    #[test]
+    fn roc_box_drops_contents_once() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        struct Counted(Rc<Cell<usize>>);
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Rc::new(Cell::new(0));
+
+        let roc_box = RocBox::new(Counted(drops.clone()));
+        let clone = roc_box.clone();
+        assert_eq!(drops.get(), 0);
+
+        drop(roc_box);
+        assert_eq!(drops.get(), 0);
+
+        drop(clone);
+        assert_eq!(drops.get(), 1);
+
+        // contents that own heap memory
+        let string = RocBox::new(RocStr::from("a string too long to be stored inline"));
+        assert_eq!(string.as_str(), "a string too long to be stored inline");
+        drop(string);
+    }
+
+    #[test]
+    fn roc_box_into_inner() {
+        use crate::with_retained_blocks;
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        #[derive(Clone)]
+        struct Counted(u32, Rc<Cell<usize>>);
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.1.set(self.1.get() + 1);
+            }
+        }
+
+        let drops = Rc::new(Cell::new(0));
+
+        // unique: the contents are moved out, and the allocation is freed without dropping them
+        let roc_box = RocBox::new(Counted(1, drops.clone()));
+        let mut contents = None;
+        with_retained_blocks(
+            || contents = Some(roc_box.into_inner()),
+            |blocks| assert_eq!(blocks.len(), 1),
+        );
+        assert_eq!(drops.get(), 0);
+        assert_eq!(contents.as_ref().map(|c| c.0), Some(1));
+        drop(contents);
+        assert_eq!(drops.get(), 1);
+
+        // shared: the contents are cloned, and the other reference keeps the allocation
+        let roc_box = RocBox::new(Counted(2, drops.clone()));
+        let other = roc_box.clone();
+        let mut contents = None;
+        with_retained_blocks(
+            || contents = Some(roc_box.into_inner_or_clone()),
+            |blocks| assert!(blocks.is_empty()),
+        );
+        assert_eq!(contents.as_ref().map(|c| c.0), Some(2));
+        assert!(other.is_unique());
+        assert_eq!(other.0, 2);
+
+        drop(contents);
+        assert_eq!(drops.get(), 2);
+
+        with_retained_blocks(
+            || assert_eq!(other.into_inner_or_clone().0, 2),
+            |blocks| assert_eq!(blocks.len(), 1),
+        );
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    fn roc_box_try_unwrap() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        struct Counted(u32, Rc<Cell<usize>>);
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.1.set(self.1.get() + 1);
+            }
+        }
+
+        let drops = Rc::new(Cell::new(0));
+
+        // unique
+        let contents = RocBox::try_unwrap(RocBox::new(Counted(1, drops.clone())))
+            .ok()
+            .unwrap();
+        assert_eq!(contents.0, 1);
+        assert_eq!(drops.get(), 0);
+        drop(contents);
+        assert_eq!(drops.get(), 1);
+
+        // shared, then unique once the other reference is dropped
+        let roc_box = RocBox::new(Counted(2, drops.clone()));
+        let other = roc_box.clone();
+        let roc_box = match RocBox::try_unwrap(roc_box) {
+            Ok(_) => panic!("a shared box must not be unwrapped"),
+            Err(roc_box) => roc_box,
+        };
+        assert!(RocBox::ptr_eq(&roc_box, &other));
+        assert_eq!(drops.get(), 1);
+
+        drop(other);
+        let contents = RocBox::try_unwrap(roc_box).ok().unwrap();
+        assert_eq!(contents.0, 2);
+        assert_eq!(drops.get(), 1);
+        drop(contents);
+        assert_eq!(drops.get(), 2);
+
+        // readonly
+        let roc_box = RocBox::new(Counted(3, drops.clone()));
+        unsafe { roc_box.set_readonly() };
+        let roc_box = match RocBox::try_unwrap(roc_box) {
+            Ok(_) => panic!("a readonly box must not be unwrapped"),
+            Err(roc_box) => roc_box,
+        };
+        assert_eq!(roc_box.0, 3);
+        drop(roc_box);
+        assert_eq!(drops.get(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "shared or readonly")]
+    fn roc_box_into_inner_shared() {
+        let roc_box = RocBox::new(1u64);
+        let _other = roc_box.clone();
+
+        roc_box.into_inner();
+    }
+
+    #[test]
+    fn roc_box_decrement_by() {
+        let roc_box = RocBox::new(42u64);
+        let clones = [roc_box.clone(), roc_box.clone()];
+
+        // the clones are handed over to the host, which gives up all three references at once
+        core::mem::forget(clones);
+        assert!(unsafe { roc_box.decrement_by(3) });
+
+        // dropping the box now releases the allocation
+        assert_eq!(*roc_box, 42);
+    }
+
+    #[test]
+    fn roc_box_decrement_by_leaves_other_references() {
+        let roc_box = RocBox::new(42u64);
+        let handed_over = roc_box.clone();
+        let kept = roc_box.clone();
+
+        // the box itself and the clone that was handed over
+        core::mem::forget(handed_over);
+        assert!(!unsafe { roc_box.decrement_by(2) });
+        drop(roc_box);
+
+        assert_eq!(*kept, 42);
+        assert_eq!(kept.strong_count(), Some(1));
+    }
+
     #[test]
     fn roc_dec_fmt() {
         assert_eq!(